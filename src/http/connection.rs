@@ -16,6 +16,7 @@ use std::borrow::Cow;
 use std::path::Path;
 use std::io;
 use std::str;
+use std::cmp;
 
 use openssl::ssl::{Ssl, SslStream, SslContext};
 use openssl::ssl::{SSL_VERIFY_PEER, SSL_VERIFY_FAIL_IF_NO_PEER_CERT};
@@ -25,7 +26,7 @@ use openssl::ssl::SslMethod;
 
 use super::session::Session;
 use super::ALPN_PROTOCOLS;
-use super::{HttpError, HttpResult, Request, HttpScheme};
+use super::{HttpError, HttpResult, Request, HttpScheme, StreamId};
 use super::transport::TransportStream;
 use super::frame::{
     Frame,
@@ -33,9 +34,13 @@ use super::frame::{
     DataFrame,
     DataFlag,
     HeadersFrame,
-    HeadersFlag,
+    Flag,
     SettingsFrame,
+    SettingsFlag,
     HttpSetting,
+    PingFrame,
+    FrameHeader,
+    ParsedHeader,
     unpack_header,
 };
 use hpack;
@@ -50,6 +55,225 @@ pub enum HttpFrame {
     DataFrame(DataFrame),
     HeadersFrame(HeadersFrame),
     SettingsFrame(SettingsFrame),
+    PingFrame(PingFrame),
+}
+
+impl HttpFrame {
+    /// Decodes the given `RawFrame` into the `HttpFrame` variant that matches
+    /// the frame type found in its header.
+    ///
+    /// # Returns
+    ///
+    /// If the length given in the frame's header does not match the length of
+    /// its payload, the `HttpError::FrameSizeError` variant is returned,
+    /// regardless of the frame type. The same goes for a SETTINGS frame whose
+    /// length is not a multiple of 6 or an ACK SETTINGS frame with a payload
+    /// (section 6.5.).
+    ///
+    /// If the frame type is unknown the `HttpError::UnknownFrameType` variant
+    /// is returned.
+    ///
+    /// If the frame type is recognized, but the frame cannot be successfully
+    /// decoded (including when it is associated with a stream it is not
+    /// allowed to be on), the `HttpError::InvalidFrame` variant is returned.
+    pub fn from_raw(raw_frame: RawFrame) -> HttpResult<HttpFrame> {
+        if !raw_frame.is_consistent() {
+            return Err(HttpError::FrameSizeError);
+        }
+        let header = raw_frame.parsed_header();
+        try!(validate_stream_association(header.frame_type, header.stream_id));
+        if header.frame_type == 0x4 {
+            let is_ack = header.flags & SettingsFlag::Ack.bitmask() != 0;
+            if header.length % 6 != 0 || (is_ack && header.length != 0) {
+                return Err(HttpError::FrameSizeError);
+            }
+        }
+
+        // TODO: The reason behind being unable to decode the frame should be
+        //       extracted and an appropriate connection-level action taken
+        //       (e.g. responding with a PROTOCOL_ERROR).
+        let frame = match header.frame_type {
+            0x0 => HttpFrame::DataFrame(try!(HttpFrame::parse_frame(raw_frame))),
+            0x1 => HttpFrame::HeadersFrame(try!(HttpFrame::parse_frame(raw_frame))),
+            0x4 => HttpFrame::SettingsFrame(try!(HttpFrame::parse_frame(raw_frame))),
+            0x6 => HttpFrame::PingFrame(try!(HttpFrame::parse_frame(raw_frame))),
+            _ => return Err(HttpError::UnknownFrameType),
+        };
+
+        Ok(frame)
+    }
+
+    /// A helper method that parses the given `RawFrame` into the given `Frame`
+    /// implementation.
+    ///
+    /// # Returns
+    ///
+    /// Failing to decode the given `Frame` from the `raw_frame`, an
+    /// `HttpError::InvalidFrame` error is returned.
+    #[inline]
+    fn parse_frame<F: Frame>(raw_frame: RawFrame) -> HttpResult<F> {
+        Frame::from_raw(raw_frame).ok_or(HttpError::InvalidFrame)
+    }
+}
+
+/// The sequence of octets that every client starts an HTTP/2 connection
+/// with (section 3.5.), before sending its SETTINGS frame.
+const CLIENT_PREFACE: &'static [u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Decodes all frames found in a captured sequence of bytes that was sent
+/// on an HTTP/2 connection in one direction.
+///
+/// If `is_server_view` is set, the bytes are those received by the server,
+/// so the client connection preface is stripped from their start, if it is
+/// there. Otherwise, the bytes are expected to contain only frames.
+///
+/// # Returns
+///
+/// One result per frame, in the order in which the frames appear, as
+/// returned by `HttpFrame::from_raw`. If the bytes end with an incomplete
+/// frame, the last result is an `HttpError::InvalidFrame`.
+pub fn parse_session(bytes: &[u8], is_server_view: bool) -> Vec<HttpResult<HttpFrame>> {
+    let mut rest = bytes;
+    if is_server_view && rest.starts_with(CLIENT_PREFACE) {
+        rest = &rest[CLIENT_PREFACE.len()..];
+    }
+
+    let mut frames = Vec::new();
+    while !rest.is_empty() {
+        let raw = match RawFrame::from_buf_ref(rest) {
+            Some(raw) => raw,
+            None => {
+                debug!("Captured session ends with an incomplete frame");
+                frames.push(Err(HttpError::InvalidFrame));
+                break;
+            },
+        };
+        rest = &rest[9 + raw.payload.len()..];
+        frames.push(HttpFrame::from_raw(raw.into()));
+    }
+
+    frames
+}
+
+/// Checks whether a frame of the given type is allowed to be associated with
+/// the stream with the given ID.
+///
+/// SETTINGS, PING and GOAWAY frames apply to the connection as a whole and
+/// have to be sent on stream 0, whereas DATA, HEADERS, PRIORITY, RST_STREAM,
+/// PUSH_PROMISE and CONTINUATION frames always belong to a stream and must
+/// not be sent on stream 0. A WINDOW_UPDATE can be sent on either. Frames of
+/// unknown types are not checked.
+///
+/// # Returns
+///
+/// `HttpError::InvalidFrame` if the association is not allowed.
+fn validate_stream_association(frame_type: u8, stream_id: StreamId) -> HttpResult<()> {
+    let valid = match frame_type {
+        0x4 | 0x6 | 0x7 => stream_id == 0,
+        0x0 | 0x1 | 0x2 | 0x3 | 0x5 | 0x9 => stream_id != 0,
+        _ => true,
+    };
+
+    if valid { Ok(()) } else { Err(HttpError::InvalidFrame) }
+}
+
+/// A struct representing the values of all settings that an HTTP/2 endpoint
+/// can communicate to its peer by sending a SETTINGS frame (section 6.5.2.).
+///
+/// A newly created `ConnectionSettings` holds the initial values of the
+/// settings, as defined by the spec.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ConnectionSettings {
+    /// The maximum size of the header compression table used to decode
+    /// header blocks.
+    pub header_table_size: u32,
+    /// Whether server push is allowed.
+    pub enable_push: bool,
+    /// The maximum number of concurrent streams. `None` means that there is
+    /// no limit.
+    pub max_concurrent_streams: Option<u32>,
+    /// The initial window size for stream-level flow control.
+    pub initial_window_size: u32,
+    /// The size of the largest frame payload that may be sent.
+    pub max_frame_size: u32,
+    /// The maximum size of a header list. `None` means that there is no
+    /// limit.
+    pub max_header_list_size: Option<u32>,
+}
+
+impl ConnectionSettings {
+    /// Creates a new `ConnectionSettings` with all settings set to their
+    /// initial values (section 6.5.2.).
+    pub fn new() -> ConnectionSettings {
+        ConnectionSettings {
+            header_table_size: 4096,
+            enable_push: true,
+            max_concurrent_streams: None,
+            initial_window_size: 65535,
+            max_frame_size: 16384,
+            max_header_list_size: None,
+        }
+    }
+
+    /// Applies all the given settings, in the given order. If the same
+    /// setting is found multiple times, the last value is the one that ends
+    /// up being applied.
+    ///
+    /// Processing the settings is atomic: either every given setting has a
+    /// valid value and all of them are applied, or none of them is.
+    ///
+    /// # Returns
+    ///
+    /// If any of the settings has a value that is not allowed by the spec,
+    /// an error is returned and the `ConnectionSettings` are left unchanged.
+    /// An initial window size above the maximum window size gives a
+    /// `HttpError::FlowControlError`, any other invalid value a
+    /// `HttpError::InvalidSettings` (section 6.5.2.).
+    pub fn apply_all(&mut self, settings: &[HttpSetting]) -> HttpResult<()> {
+        for setting in settings.iter() {
+            try!(ConnectionSettings::validate(setting));
+        }
+        for setting in settings.iter() {
+            self.apply(setting);
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the value of the given setting is within the bounds
+    /// defined in section 6.5.2., returning the error that the spec
+    /// prescribes if it is not.
+    fn validate(setting: &HttpSetting) -> HttpResult<()> {
+        match *setting {
+            HttpSetting::EnablePush(val) if val > 1 => {
+                Err(HttpError::InvalidSettings)
+            },
+            HttpSetting::InitialWindowSize(val) if val > 0x7FFFFFFF => {
+                Err(HttpError::FlowControlError)
+            },
+            HttpSetting::MaxFrameSize(val) if val < 16384 || val > 0xFFFFFF => {
+                Err(HttpError::InvalidSettings)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies a single setting, assuming that its value was already
+    /// validated.
+    fn apply(&mut self, setting: &HttpSetting) {
+        match *setting {
+            HttpSetting::HeaderTableSize(val) => self.header_table_size = val,
+            HttpSetting::EnablePush(val) => self.enable_push = val == 1,
+            HttpSetting::MaxConcurrentStreams(val) => {
+                self.max_concurrent_streams = Some(val);
+            },
+            HttpSetting::InitialWindowSize(val) => self.initial_window_size = val,
+            HttpSetting::MaxFrameSize(val) => self.max_frame_size = val,
+            HttpSetting::MaxHeaderListSize(val) => {
+                self.max_header_list_size = Some(val);
+            },
+        }
+    }
 }
 
 /// The struct implements the HTTP/2 connection level logic.
@@ -112,23 +336,25 @@ impl<S> HttpConnection<S> where S: TransportStream {
     /// If a frame is successfully read and parsed, returns the frame wrapped
     /// in the appropriate variant of the `HttpFrame` enum.
     pub fn recv_frame(&mut self) -> HttpResult<HttpFrame> {
-        let header = unpack_header(&try!(self.read_header_bytes()));
-        debug!("Received frame header {:?}", header);
+        HttpFrame::from_raw(try!(self.recv_raw_frame()))
+    }
 
-        let payload = try!(self.read_payload(header.0));
-        let raw_frame = RawFrame::with_payload(header, payload);
+    /// Reads the next frame from the transport layer, without decoding it.
+    ///
+    /// # Returns
+    ///
+    /// Any IO errors raised by the underlying transport layer are wrapped in a
+    /// `HttpError::IoError` variant and propagated upwards.
+    ///
+    /// Otherwise, returns the `RawFrame` made up of the frame's header and its
+    /// entire payload.
+    pub fn recv_raw_frame(&mut self) -> HttpResult<RawFrame> {
+        let header = ParsedHeader::from(unpack_header(&try!(self.read_header_bytes())));
+        debug!("Received frame header {:?}", header);
 
-        // TODO: The reason behind being unable to decode the frame should be
-        //       extracted and an appropriate connection-level action taken
-        //       (e.g. responding with a PROTOCOL_ERROR).
-        let frame = match header.1 {
-            0x0 => HttpFrame::DataFrame(try!(self.parse_frame(raw_frame))),
-            0x1 => HttpFrame::HeadersFrame(try!(self.parse_frame(raw_frame))),
-            0x4 => HttpFrame::SettingsFrame(try!(self.parse_frame(raw_frame))),
-            _ => return Err(HttpError::UnknownFrameType),
-        };
+        let payload = try!(self.read_payload(header.length));
 
-        Ok(frame)
+        Ok(RawFrame::with_payload(header.into(), payload))
     }
 
     /// Reads the header bytes of the next frame from the underlying stream.
@@ -166,18 +392,6 @@ impl<S> HttpConnection<S> where S: TransportStream {
 
         Ok(buf)
     }
-
-    /// A helper method that parses the given `RawFrame` into the given `Frame`
-    /// implementation.
-    ///
-    /// # Returns
-    ///
-    /// Failing to decode the given `Frame` from the `raw_frame`, an
-    /// `HttpError::InvalidFrame` error is returned.
-    #[inline]
-    fn parse_frame<F: Frame>(&self, raw_frame: RawFrame) -> HttpResult<F> {
-        Frame::from_raw(raw_frame).ok_or(HttpError::InvalidFrame)
-    }
 }
 
 /// A marker trait for errors raised by attempting to establish an HTTP/2
@@ -387,6 +601,29 @@ pub struct ClientConnection<TS, S>
     /// of callbacks that are triggered by the connection when different states
     /// in the HTTP/2 communication arise.
     pub session: S,
+    /// The settings that the peer has communicated to us.
+    peer_settings: ConnectionSettings,
+    /// The settings that we communicate to the peer. Changing them only has
+    /// an effect before the connection is initialized (i.e. before `init`
+    /// sends the client preface).
+    pub local_settings: ConnectionSettings,
+    /// Whether the peer's initial SETTINGS frame was received (and
+    /// acknowledged).
+    peer_settings_received: bool,
+    /// Whether the peer has acknowledged the SETTINGS frame that we sent.
+    local_settings_acked: bool,
+    /// The local settings that were sent to the peer, but not yet
+    /// acknowledged.
+    pending_local_settings: Option<ConnectionSettings>,
+    /// The local settings that the peer has acknowledged and that are,
+    /// therefore, in effect. Until the first ACK, these are the defaults.
+    acked_local_settings: ConnectionSettings,
+    /// The opaque data of the PING frames that were sent, but for which no
+    /// ACK was received yet.
+    outstanding_pings: Vec<Vec<u8>>,
+    /// When enabled, the headers of all frames sent or received on the
+    /// connection, in the order in which they were handled.
+    frame_log: Option<Vec<(bool, FrameHeader)>>,
 }
 
 impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
@@ -399,6 +636,18 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
             encoder: hpack::Encoder::new(),
             decoder: hpack::Decoder::new(),
             session: session,
+            peer_settings: ConnectionSettings::new(),
+            local_settings: ConnectionSettings {
+                // The client does not support server push (yet).
+                enable_push: false,
+                ..ConnectionSettings::new()
+            },
+            peer_settings_received: false,
+            local_settings_acked: false,
+            pending_local_settings: None,
+            acked_local_settings: ConnectionSettings::new(),
+            outstanding_pings: Vec::new(),
+            frame_log: None,
         }
     }
 
@@ -414,6 +663,132 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
         self.conn.scheme
     }
 
+    /// Returns the maximum number of concurrent streams that the peer allows
+    /// us to open, if the peer has set a limit.
+    #[inline]
+    pub fn max_concurrent_streams(&self) -> Option<u32> {
+        self.peer_settings.max_concurrent_streams
+    }
+
+    /// Returns the largest frame payload that the peer is willing to receive.
+    #[inline]
+    pub fn max_frame_size(&self) -> u32 {
+        self.peer_settings.max_frame_size
+    }
+
+    /// Returns whether the initial SETTINGS exchange has been completed,
+    /// i.e. both endpoints have sent their SETTINGS and both of those have
+    /// been acknowledged.
+    ///
+    /// This is only advisory: `send_request` does not check it. A client that
+    /// wants its requests to respect the peer's settings (e.g. its limit on
+    /// concurrent streams) should keep calling `handle_next_frame` after
+    /// `init` until the connection is ready, before sending any requests.
+    pub fn is_ready(&self) -> bool {
+        self.peer_settings_received && self.local_settings_acked
+    }
+
+    /// Returns the local settings that the peer has acknowledged.
+    ///
+    /// Settings changed in `local_settings` only show up here once they have
+    /// been sent to the peer and the peer has sent back a SETTINGS ACK.
+    #[inline]
+    pub fn acked_local_settings(&self) -> &ConnectionSettings {
+        &self.acked_local_settings
+    }
+
+    /// Starts recording the header of each frame that is sent or received on
+    /// the connection from this point on.
+    ///
+    /// This is meant as a debugging aid: the recorded log can be dumped in
+    /// order to reproduce the exact frame sequence that led to an error.
+    pub fn enable_frame_log(&mut self) {
+        if self.frame_log.is_none() {
+            self.frame_log = Some(Vec::new());
+        }
+    }
+
+    /// Returns the headers of all frames handled since the frame log was
+    /// enabled, in order. The first element of each pair is `true` for frames
+    /// that were received and `false` for frames that were sent.
+    ///
+    /// If the frame log was never enabled, the slice is empty.
+    pub fn frame_log(&self) -> &[(bool, FrameHeader)] {
+        match self.frame_log {
+            Some(ref log) => log,
+            None => &[],
+        }
+    }
+
+    /// Appends the given header to the frame log, if it is enabled.
+    #[inline]
+    fn log_frame(&mut self, receiving: bool, header: FrameHeader) {
+        if let Some(ref mut log) = self.frame_log {
+            log.push((receiving, header));
+        }
+    }
+
+    /// Sends the given frame on the underlying `HttpConnection`, recording
+    /// it in the frame log (if enabled) once it is written.
+    fn send_frame<F: Frame>(&mut self, frame: F) -> HttpResult<()> {
+        let header = frame.get_header();
+        try!(self.conn.send_frame(frame));
+        self.log_frame(false, header);
+        Ok(())
+    }
+
+    /// Reads the next frame from the underlying `HttpConnection` and decodes
+    /// it by way of `decode_frame`.
+    fn recv_frame(&mut self) -> HttpResult<HttpFrame> {
+        let raw_frame = try!(self.conn.recv_raw_frame());
+        self.decode_frame(raw_frame)
+    }
+
+    /// Handles the given `RawFrame` as one that was received on the
+    /// connection: it is recorded in the frame log (if enabled) and decoded.
+    ///
+    /// # Returns
+    ///
+    /// The same as `HttpFrame::from_raw` for the given frame.
+    fn decode_frame(&mut self, raw_frame: RawFrame) -> HttpResult<HttpFrame> {
+        // The frame is logged before it is decoded, so that frames which fail
+        // to parse still show up in the log.
+        self.log_frame(true, raw_frame.header);
+
+        HttpFrame::from_raw(raw_frame)
+    }
+
+    /// Sends a PING frame carrying the given opaque data to the peer.
+    ///
+    /// The data is remembered until a PING ACK carrying the same data is
+    /// matched by `match_ping_ack`.
+    pub fn send_ping(&mut self, data: [u8; 8]) -> HttpResult<()> {
+        try!(self.send_frame(PingFrame::with_data(data)));
+        self.outstanding_pings.push(data.to_vec());
+        Ok(())
+    }
+
+    /// Checks whether the given PING ACK corresponds to a PING that was sent
+    /// by `send_ping` and is still outstanding.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the ACK matches an outstanding PING, which is then no longer
+    /// considered outstanding. `false` if the frame is not an ACK or if its
+    /// data does not match any outstanding PING.
+    pub fn match_ping_ack(&mut self, frame: &PingFrame) -> bool {
+        if !frame.is_ack() {
+            return false;
+        }
+        match self.outstanding_pings.iter().position(|data| *data == frame.data) {
+            Some(pos) => {
+                self.outstanding_pings.remove(pos);
+                true
+            },
+            None => false,
+        }
+    }
+
     /// Performs the initialization of the `ClientConnection`.
     ///
     /// Sends the client preface, followed by validating the receipt of the
@@ -434,21 +809,35 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
     fn write_preface(&mut self) -> HttpResult<()> {
         // The first part of the client preface is always this sequence of 24
         // raw octets.
-        let preface = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
-        try!(self.conn.stream.write(preface));
+        try!(self.conn.stream.write(CLIENT_PREFACE));
 
         // It is followed by the client's settings.
-        let settings = {
-            let mut frame = SettingsFrame::new();
-            frame.add_setting(HttpSetting::EnablePush(0));
-            frame
-        };
-        try!(self.conn.send_frame(settings));
+        let settings = self.local_settings_frame();
+        try!(self.send_frame(settings));
+        self.pending_local_settings = Some(self.local_settings);
         debug!("Sent client preface");
 
         Ok(())
     }
 
+    /// Returns a (non-ACK) `SettingsFrame` that communicates the current
+    /// local settings of the connection to the peer.
+    ///
+    /// The frame contains the push-enable, max concurrent streams (only if
+    /// there is a limit), initial window size, and max frame size settings.
+    pub fn local_settings_frame(&self) -> SettingsFrame {
+        let settings = &self.local_settings;
+        let mut frame = SettingsFrame::new();
+        frame.add_setting(HttpSetting::EnablePush(settings.enable_push as u32));
+        if let Some(max_concurrent_streams) = settings.max_concurrent_streams {
+            frame.add_setting(HttpSetting::MaxConcurrentStreams(max_concurrent_streams));
+        }
+        frame.add_setting(HttpSetting::InitialWindowSize(settings.initial_window_size));
+        frame.add_setting(HttpSetting::MaxFrameSize(settings.max_frame_size));
+
+        frame
+    }
+
     /// Reads and handles the server preface from the underlying HTTP/2
     /// connection.
     ///
@@ -462,7 +851,7 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
     /// Additionally, if it is not possible to decode the server preface,
     /// it returns the `HttpError::UnableToConnect` variant.
     fn read_preface(&mut self) -> HttpResult<()> {
-        match self.conn.recv_frame() {
+        match self.recv_frame() {
             Ok(HttpFrame::SettingsFrame(settings)) => {
                 debug!("Correctly received a SETTINGS frame from the server");
                 try!(self.handle_settings_frame(settings));
@@ -484,32 +873,62 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
     /// # Note
     ///
     /// Request body is ignored for now.
+    ///
+    /// The request is sent even if the connection is not `is_ready` yet.
     pub fn send_request(&mut self, req: Request) -> HttpResult<()> {
         let headers_fragment = self.encoder.encode(&req.headers);
         // For now, sending header fragments larger than 16kB is not supported
         // (i.e. the encoded representation cannot be split into CONTINUATION
         // frames).
-        let mut frame = HeadersFrame::new(headers_fragment, req.stream_id);
-        frame.set_flag(HeadersFlag::EndHeaders);
         // Since we are not supporting methods which require request bodies to
         // be sent, we end the stream from this side already.
         // TODO: Support bodies!
-        frame.set_flag(HeadersFlag::EndStream);
+        let frame = HeadersFrame::request(req.stream_id, headers_fragment, true);
 
         // Sending this HEADER frame opens the new stream and is equivalent to
         // sending the given request to the server.
-        try!(self.conn.send_frame(frame));
+        try!(self.send_frame(frame));
 
         Ok(())
     }
 
+    /// Packs the given chunks of data, which are to be sent on the given
+    /// stream, into as few DATA frames as possible.
+    ///
+    /// The chunks are concatenated and split so that no frame carries more
+    /// data than the peer's max frame size. Only the last frame has the
+    /// END_STREAM flag set. If there is no data at all, a single empty frame
+    /// that ends the stream is returned.
+    pub fn coalesce_data(&self, stream_id: StreamId, chunks: Vec<Vec<u8>>)
+            -> Vec<DataFrame> {
+        let max_size = self.peer_settings.max_frame_size as usize;
+        let mut frames: Vec<DataFrame> = Vec::new();
+        let mut current = DataFrame::new(stream_id);
+        for chunk in chunks.into_iter() {
+            let mut rest = &chunk[..];
+            while !rest.is_empty() {
+                if current.data.len() == max_size {
+                    frames.push(current);
+                    current = DataFrame::new(stream_id);
+                }
+                let take = cmp::min(max_size - current.data.len(), rest.len());
+                current.data.extend(rest[..take].to_vec().into_iter());
+                rest = &rest[take..];
+            }
+        }
+        current.set_flag(DataFlag::EndStream);
+        frames.push(current);
+
+        frames
+    }
+
     /// Fully handle the next incoming frame, blocking to read it from the
     /// underlying transport stream if not available yet.
     ///
     /// All communication errors are propagated.
     pub fn handle_next_frame(&mut self) -> HttpResult<()> {
         debug!("Waiting for frame...");
-        let frame = match self.conn.recv_frame() {
+        let frame = match self.recv_frame() {
             Ok(frame) => frame,
             Err(HttpError::UnknownFrameType) => {
                 debug!("Ignoring unknown frame type");
@@ -524,6 +943,27 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
         self.handle_frame(frame)
     }
 
+    /// Handles each of the given raw frames in order, as if they had been
+    /// received on the connection one after the other.
+    ///
+    /// Unlike `handle_next_frame`, processing does not stop at the first
+    /// frame that fails: every frame is handled and the caller gets back one
+    /// result per frame, in the same order, which shows exactly which frames
+    /// in the sequence could not be handled. Frames of unknown types are
+    /// ignored, giving an `Ok`.
+    ///
+    /// The frames are recorded in the frame log (if enabled), just like the
+    /// frames read from the transport stream.
+    pub fn handle_frames(&mut self, frames: Vec<RawFrame>) -> Vec<HttpResult<()>> {
+        frames.into_iter().map(|raw| {
+            match self.decode_frame(raw) {
+                Ok(frame) => self.handle_frame(frame),
+                Err(HttpError::UnknownFrameType) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }).collect()
+    }
+
     /// Private helper method that actually handles a received frame.
     fn handle_frame(&mut self, frame: HttpFrame) -> HttpResult<()> {
         match frame {
@@ -538,7 +978,11 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
             HttpFrame::SettingsFrame(frame) => {
                 debug!("Settings frame received");
                 self.handle_settings_frame(frame)
-            }
+            },
+            HttpFrame::PingFrame(frame) => {
+                debug!("Ping frame received");
+                self.handle_ping_frame(frame)
+            },
         }
     }
 
@@ -569,12 +1013,44 @@ impl<TS, S> ClientConnection<TS, S> where TS: TransportStream, S: Session {
     }
 
     /// Private helper method that handles a received `SettingsFrame`.
+    ///
+    /// The new settings are only acknowledged if all of them could be
+    /// applied. If any of them is invalid, none of them takes effect and
+    /// an error is returned.
     fn handle_settings_frame(&mut self, frame: SettingsFrame) -> HttpResult<()> {
         if !frame.is_ack() {
-            // TODO: Actually handle the settings change before
-            //       sending out the ACK.
+            try!(self.peer_settings.apply_all(&frame.settings));
             debug!("Sending a SETTINGS ack");
-            try!(self.conn.send_frame(SettingsFrame::new_ack()));
+            try!(self.send_frame(SettingsFrame::new_ack()));
+            self.peer_settings_received = true;
+        } else {
+            // An ACK with a payload never makes it past `HttpFrame::from_raw`.
+            debug_assert!(frame.settings.is_empty());
+            match self.pending_local_settings.take() {
+                Some(settings) => {
+                    debug!("Our SETTINGS were acknowledged");
+                    self.acked_local_settings = settings;
+                    self.local_settings_acked = true;
+                },
+                None => debug!("Received a SETTINGS ack without any SETTINGS in flight"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Private helper method that handles a received `PingFrame`.
+    ///
+    /// A PING that is not an ACK is answered by a PING ACK carrying the same
+    /// opaque data, as required by the spec (section 6.7.).
+    fn handle_ping_frame(&mut self, frame: PingFrame) -> HttpResult<()> {
+        if !frame.is_ack() {
+            debug!("Sending a PING ack");
+            let mut ack = PingFrame::new_ack();
+            ack.data = frame.data;
+            try!(self.send_frame(ack));
+        } else if !self.match_ping_ack(&frame) {
+            debug!("Received a PING ack that matches no outstanding PING");
         }
 
         Ok(())
@@ -587,15 +1063,18 @@ mod tests {
     use std::io;
 
     use super::super::frame::{
-        Frame, DataFrame, HeadersFrame,
+        Frame, DataFrame, DataFlag, HeadersFrame,
         SettingsFrame,
+        HttpSetting,
+        PingFrame,
         pack_header,
         RawFrame,
     };
-    use super::{HttpConnection, HttpFrame, ClientConnection};
+    use super::{HttpConnection, HttpFrame, ClientConnection, ConnectionSettings};
+    use super::{validate_stream_association, parse_session};
     use super::super::transport::TransportStream;
     use super::super::{HttpError, HttpScheme, Request, StreamId, Header};
-    use super::super::session::Session;
+    use super::super::session::{Session, Stream, DefaultSession, DefaultStream};
     use hpack;
 
     /// A helper stub implementation of a `TransportStream`.
@@ -779,6 +1258,7 @@ mod tests {
                 &HttpFrame::DataFrame(ref frame) => frame.serialize(),
                 &HttpFrame::HeadersFrame(ref frame) => frame.serialize(),
                 &HttpFrame::SettingsFrame(ref frame) => frame.serialize(),
+                &HttpFrame::PingFrame(ref frame) => frame.serialize(),
             };
             buf.extend(serialized.into_iter());
         }
@@ -941,6 +1421,94 @@ mod tests {
         });
     }
 
+    /// Tests that frames which have to be on stream 0 are rejected on any
+    /// other stream and vice versa.
+    #[test]
+    fn test_validate_stream_association() {
+        // (frame type, allowed on stream 0, allowed on a non-zero stream)
+        let rules = vec![
+            (0x0, false, true),  // DATA
+            (0x1, false, true),  // HEADERS
+            (0x2, false, true),  // PRIORITY
+            (0x3, false, true),  // RST_STREAM
+            (0x4, true, false),  // SETTINGS
+            (0x5, false, true),  // PUSH_PROMISE
+            (0x6, true, false),  // PING
+            (0x7, true, false),  // GOAWAY
+            (0x8, true, true),   // WINDOW_UPDATE
+            (0x9, false, true),  // CONTINUATION
+            (0xA, true, true),   // Unknown
+        ];
+
+        for &(frame_type, on_zero, on_stream) in rules.iter() {
+            assert_eq!(validate_stream_association(frame_type, 0).is_ok(), on_zero);
+            assert_eq!(validate_stream_association(frame_type, 3).is_ok(), on_stream);
+        }
+    }
+
+    /// Tests that a SETTINGS frame on a non-zero stream is rejected by the
+    /// `HttpConnection` as invalid.
+    #[test]
+    fn test_read_settings_frame_on_stream() {
+        let mut conn = build_http_conn(&{
+            let mut buf: Vec<u8> = Vec::new();
+            buf.extend(pack_header(&(0, 0x4, 0, 1)).to_vec().into_iter());
+            buf
+        });
+
+        assert_eq!(conn.recv_frame().err().unwrap(), HttpError::InvalidFrame);
+    }
+
+    /// Tests that a captured session, as seen by the server, is decoded into
+    /// the sequence of frames it contains, with the preface stripped.
+    #[test]
+    fn test_parse_session() {
+        let frames = vec![
+            HttpFrame::SettingsFrame(SettingsFrame::new()),
+            HttpFrame::HeadersFrame(HeadersFrame::new(vec![1, 2, 3], 1)),
+        ];
+        let mut bytes: Vec<u8> = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        bytes.extend(build_stub_from_frames(&frames).into_iter());
+
+        let parsed = parse_session(&bytes, true);
+
+        assert_eq!(parsed.len(), 2);
+        for (res, expected) in parsed.into_iter().zip(frames.into_iter()) {
+            assert_eq!(res.ok().unwrap(), expected);
+        }
+    }
+
+    /// Tests that decoding a captured session reports invalid, unknown and
+    /// incomplete frames, and that the preface is only stripped in the
+    /// server's view.
+    #[test]
+    fn test_parse_session_errors() {
+        let frames = vec![
+            HttpFrame::SettingsFrame(SettingsFrame::new()),
+        ];
+        let mut bytes = build_stub_from_frames(&frames);
+        // DATA on stream 0
+        bytes.extend(pack_header(&(0, 0x0, 0, 0)).to_vec().into_iter());
+        // Unknown frame type
+        bytes.extend(pack_header(&(0, 0xA, 0, 1)).to_vec().into_iter());
+        // A frame whose payload is cut off
+        bytes.extend(pack_header(&(5, 0x0, 0, 1)).to_vec().into_iter());
+        bytes.extend(vec![1, 2].into_iter());
+
+        let parsed = parse_session(&bytes, false);
+
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed[0].as_ref().ok().unwrap(), &frames[0]);
+        assert_eq!(parsed[1].as_ref().err().unwrap(), &HttpError::InvalidFrame);
+        assert_eq!(parsed[2].as_ref().err().unwrap(), &HttpError::UnknownFrameType);
+        assert_eq!(parsed[3].as_ref().err().unwrap(), &HttpError::InvalidFrame);
+
+        // The client never receives the preface, so in its view the preface
+        // is not a valid frame.
+        let parsed = parse_session(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n", false);
+        assert!(parsed[0].is_err());
+    }
+
     /// Tests that it is possible to write a single frame to the connection.
     #[test]
     fn test_write_single_frame() {
@@ -954,6 +1522,7 @@ mod tests {
             let _ = match frame {
                 HttpFrame::DataFrame(frame) => conn.send_frame(frame),
                 HttpFrame::SettingsFrame(frame) => conn.send_frame(frame),
+                HttpFrame::PingFrame(frame) => conn.send_frame(frame),
                 HttpFrame::HeadersFrame(frame) => conn.send_frame(frame),
             };
         }
@@ -978,6 +1547,7 @@ mod tests {
             let _ = match frame {
                 HttpFrame::DataFrame(frame) => conn.send_frame(frame),
                 HttpFrame::SettingsFrame(frame) => conn.send_frame(frame),
+                HttpFrame::PingFrame(frame) => conn.send_frame(frame),
                 HttpFrame::HeadersFrame(frame) => conn.send_frame(frame),
             };
         }
@@ -999,6 +1569,7 @@ mod tests {
             let res = match frame {
                 HttpFrame::DataFrame(frame) => conn.send_frame(frame),
                 HttpFrame::SettingsFrame(frame) => conn.send_frame(frame),
+                HttpFrame::PingFrame(frame) => conn.send_frame(frame),
                 HttpFrame::HeadersFrame(frame) => conn.send_frame(frame),
             };
 
@@ -1016,7 +1587,7 @@ mod tests {
     /// Panics if unable to obtain such a frame.
     fn get_frame_from_buf<F: Frame>(buf: &[u8]) -> (F, usize) {
         let raw = RawFrame::from_buf(buf).unwrap();
-        let len = raw.header.0 as usize;
+        let len = raw.parsed_header().length as usize;
         let frame = Frame::from_raw(raw).unwrap();
 
         (frame, len + 9)
@@ -1196,4 +1767,634 @@ mod tests {
         assert_eq!(conn.session.curr_chunk, 2);
         assert_eq!(conn.session.curr_header, 1);
     }
+
+    /// Tests that `ConnectionSettings` apply all valid settings, but leave
+    /// everything unchanged if any of the given settings is invalid.
+    #[test]
+    fn test_connection_settings_apply_all() {
+        {
+            let mut settings = ConnectionSettings::new();
+
+            settings.apply_all(&[
+                HttpSetting::MaxConcurrentStreams(10),
+                HttpSetting::InitialWindowSize(100),
+                HttpSetting::EnablePush(0),
+                HttpSetting::MaxFrameSize(20000),
+            ]).unwrap();
+
+            assert_eq!(settings.max_concurrent_streams, Some(10));
+            assert_eq!(settings.initial_window_size, 100);
+            assert!(!settings.enable_push);
+            assert_eq!(settings.max_frame_size, 20000);
+        }
+        {
+            // The window size is larger than the maximum window size...
+            let mut settings = ConnectionSettings::new();
+
+            let res = settings.apply_all(&[
+                HttpSetting::MaxConcurrentStreams(10),
+                HttpSetting::InitialWindowSize(1 << 31),
+            ]);
+
+            assert_eq!(res.err().unwrap(), HttpError::FlowControlError);
+            // ...so not even the valid setting got applied.
+            assert_eq!(settings, ConnectionSettings::new());
+        }
+        {
+            // EnablePush can only be 0 or 1.
+            let mut settings = ConnectionSettings::new();
+
+            let res = settings.apply_all(&[
+                HttpSetting::EnablePush(0),
+                HttpSetting::EnablePush(2),
+            ]);
+
+            assert_eq!(res.err().unwrap(), HttpError::InvalidSettings);
+            assert_eq!(settings, ConnectionSettings::new());
+        }
+        {
+            // MaxFrameSize has to be between 2^14 and 2^24-1 (inclusive).
+            let mut settings = ConnectionSettings::new();
+
+            let res = settings.apply_all(&[HttpSetting::MaxFrameSize(1024)]);
+            assert_eq!(res.err().unwrap(), HttpError::InvalidSettings);
+            let res = settings.apply_all(&[HttpSetting::MaxFrameSize(1 << 24)]);
+            assert_eq!(res.err().unwrap(), HttpError::InvalidSettings);
+            assert_eq!(settings, ConnectionSettings::new());
+
+            settings.apply_all(&[HttpSetting::MaxFrameSize(16384)]).unwrap();
+            assert_eq!(settings.max_frame_size, 16384);
+            settings.apply_all(&[HttpSetting::MaxFrameSize((1 << 24) - 1)]).unwrap();
+            assert_eq!(settings.max_frame_size, (1 << 24) - 1);
+        }
+    }
+
+    /// Tests that an initial window size of 2^31 is rejected as a flow
+    /// control error, while 2^31-1 is still accepted.
+    #[test]
+    fn test_connection_settings_initial_window_size_too_large() {
+        let mut settings = ConnectionSettings::new();
+
+        let res = settings.apply_all(&[HttpSetting::InitialWindowSize(1 << 31)]);
+        assert_eq!(res.err().unwrap(), HttpError::FlowControlError);
+        assert_eq!(settings, ConnectionSettings::new());
+
+        settings.apply_all(&[HttpSetting::InitialWindowSize((1 << 31) - 1)]).unwrap();
+        assert_eq!(settings.initial_window_size, (1 << 31) - 1);
+    }
+
+    /// Tests that when the `ClientConnection` receives a SETTINGS frame with
+    /// an invalid setting, none of the settings found in the frame take
+    /// effect and the frame is not acknowledged.
+    #[test]
+    fn test_client_conn_settings_applied_atomically() {
+        let frames: Vec<HttpFrame> = vec![
+            HttpFrame::SettingsFrame({
+                let mut frame = SettingsFrame::new();
+                frame.add_setting(HttpSetting::MaxConcurrentStreams(10));
+                frame.add_setting(HttpSetting::InitialWindowSize(1 << 31));
+                frame
+            }),
+            HttpFrame::SettingsFrame({
+                let mut frame = SettingsFrame::new();
+                frame.add_setting(HttpSetting::MaxConcurrentStreams(10));
+                frame
+            }),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+
+        // The first frame contains an invalid setting...
+        assert_eq!(
+            conn.handle_next_frame().err().unwrap(),
+            HttpError::FlowControlError);
+        // ...so nothing was applied and no ACK was sent.
+        assert_eq!(conn.peer_settings, ConnectionSettings::new());
+        assert_eq!(conn.conn.stream.get_written().len(), 0);
+
+        // The second one is fine.
+        conn.handle_next_frame().ok().unwrap();
+        assert_eq!(conn.peer_settings.max_concurrent_streams, Some(10));
+        let (frame, _): (SettingsFrame, _) =
+            get_frame_from_buf(conn.conn.stream.get_written());
+        assert!(frame.is_ack());
+    }
+
+    /// Tests that when a SETTINGS frame contains the same setting multiple
+    /// times, it is not considered an error and the last value wins.
+    #[test]
+    fn test_client_conn_settings_duplicates_last_wins() {
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&{
+                // MAX_CONCURRENT_STREAMS = 10, followed by
+                // MAX_CONCURRENT_STREAMS = 20
+                let payload = vec![0, 3, 0, 0, 0, 10, 0, 3, 0, 0, 0, 20];
+                let mut buf: Vec<u8> = Vec::new();
+                buf.extend(pack_header(&(payload.len() as u32, 4, 0, 0)).to_vec().into_iter());
+                buf.extend(payload.into_iter());
+                buf
+            }),
+            TestSession::new());
+
+        conn.handle_next_frame().ok().unwrap();
+
+        assert_eq!(conn.peer_settings.max_concurrent_streams, Some(20));
+    }
+
+    /// Tests that the getters for the peer's limits report the defaults until
+    /// the peer's SETTINGS are received and the new values afterwards.
+    #[test]
+    fn test_client_conn_peer_limits() {
+        let frames = vec![
+            HttpFrame::SettingsFrame({
+                let mut frame = SettingsFrame::new();
+                frame.add_setting(HttpSetting::MaxConcurrentStreams(100));
+                frame.add_setting(HttpSetting::MaxFrameSize(32768));
+                frame
+            }),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+        assert_eq!(conn.max_concurrent_streams(), None);
+        assert_eq!(conn.max_frame_size(), 16384);
+
+        conn.handle_next_frame().ok().unwrap();
+
+        assert_eq!(conn.max_concurrent_streams(), Some(100));
+        assert_eq!(conn.max_frame_size(), 32768);
+    }
+
+    /// Tests that the `ClientConnection` emits a SETTINGS frame that reflects
+    /// its configured local settings.
+    #[test]
+    fn test_client_conn_local_settings_frame() {
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&vec![]), TestSession::new());
+        conn.local_settings.max_concurrent_streams = Some(100);
+        conn.local_settings.initial_window_size = 1000;
+        conn.local_settings.max_frame_size = 20000;
+
+        let frame = conn.local_settings_frame();
+
+        assert!(!frame.is_ack());
+        // The frame is parsed back into the same settings values.
+        let serialized = frame.serialize();
+        let (parsed, _): (SettingsFrame, _) = get_frame_from_buf(&serialized);
+        let mut settings = ConnectionSettings::new();
+        settings.apply_all(&parsed.settings).unwrap();
+        assert_eq!(settings.max_concurrent_streams, Some(100));
+        assert_eq!(settings.initial_window_size, 1000);
+        assert_eq!(settings.max_frame_size, 20000);
+        assert!(!settings.enable_push);
+    }
+
+    /// Tests that the `ClientConnection` only reports being ready once both
+    /// sides have sent their SETTINGS and received an ACK for them.
+    #[test]
+    fn test_client_conn_is_ready() {
+        let frames = vec![
+            HttpFrame::SettingsFrame(SettingsFrame::new()),
+            HttpFrame::SettingsFrame(SettingsFrame::new_ack()),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+        assert!(!conn.is_ready());
+
+        // Sends our SETTINGS, receives and ACKs the server's SETTINGS...
+        conn.init().ok().unwrap();
+        // ...but the server still hasn't acknowledged ours.
+        assert!(!conn.is_ready());
+
+        // Now it has.
+        conn.handle_next_frame().ok().unwrap();
+        assert!(conn.is_ready());
+    }
+
+    /// Tests the intended use of `is_ready`: after `init`, frames are handled
+    /// until the SETTINGS exchange completes, at which point the peer's
+    /// settings are known and requests can be sent.
+    #[test]
+    fn test_client_conn_send_request_once_ready() {
+        let frames = vec![
+            HttpFrame::SettingsFrame({
+                let mut frame = SettingsFrame::new();
+                frame.add_setting(HttpSetting::MaxConcurrentStreams(1));
+                frame
+            }),
+            HttpFrame::SettingsFrame(SettingsFrame::new_ack()),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+
+        conn.init().ok().unwrap();
+        while !conn.is_ready() {
+            conn.handle_next_frame().ok().unwrap();
+        }
+        // The peer's limit is now known...
+        assert_eq!(conn.max_concurrent_streams(), Some(1));
+        // ...so a request can be sent within it.
+        let written_before = conn.conn.stream.get_written().len();
+        conn.send_request(Request {
+            stream_id: 1,
+            headers: vec![(b":method".to_vec(), b"GET".to_vec())],
+            body: Vec::new(),
+        }).unwrap();
+
+        let written = &conn.conn.stream.get_written()[written_before..];
+        let (frame, sz): (HeadersFrame, _) = get_frame_from_buf(written);
+        assert_eq!(frame.get_stream_id(), 1);
+        assert_eq!(sz, written.len());
+    }
+
+    /// Tests that a SETTINGS ACK received before we sent any SETTINGS does not
+    /// make the connection ready.
+    #[test]
+    fn test_client_conn_unsolicited_settings_ack() {
+        let frames = vec![
+            HttpFrame::SettingsFrame(SettingsFrame::new()),
+            HttpFrame::SettingsFrame(SettingsFrame::new_ack()),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+
+        // The peer's SETTINGS and an ACK, without the client preface ever
+        // having been sent.
+        conn.handle_next_frame().ok().unwrap();
+        conn.handle_next_frame().ok().unwrap();
+
+        assert!(!conn.is_ready());
+        assert_eq!(conn.acked_local_settings(), &ConnectionSettings::new());
+    }
+
+    /// Tests that the local settings sent in the preface only take effect once
+    /// the peer acknowledges them.
+    #[test]
+    fn test_client_conn_acked_local_settings() {
+        let frames = vec![
+            HttpFrame::SettingsFrame(SettingsFrame::new()),
+            HttpFrame::SettingsFrame(SettingsFrame::new_ack()),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+        conn.local_settings.initial_window_size = 1024;
+        conn.local_settings.max_concurrent_streams = Some(10);
+        let sent = conn.local_settings;
+
+        conn.init().ok().unwrap();
+        // Sent, but not yet acknowledged
+        assert_eq!(conn.acked_local_settings(), &ConnectionSettings::new());
+        // Changing the local settings after sending them has no effect on
+        // what ends up being acknowledged.
+        conn.local_settings.initial_window_size = 1;
+
+        conn.handle_next_frame().ok().unwrap();
+        assert_eq!(conn.acked_local_settings(), &sent);
+    }
+
+    /// Tests that the `ClientConnection` answers a PING with an ACK that
+    /// echoes the same opaque data, and does not answer a PING ACK.
+    #[test]
+    fn test_client_conn_ping_echoes_data() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let frames = vec![
+            HttpFrame::PingFrame(PingFrame::with_data(data)),
+            HttpFrame::PingFrame({
+                let mut ack = PingFrame::new_ack();
+                ack.data = data.to_vec();
+                ack
+            }),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+
+        conn.handle_next_frame().ok().unwrap();
+        conn.handle_next_frame().ok().unwrap();
+
+        let written = conn.conn.stream.get_written();
+        let (frame, sz): (PingFrame, _) = get_frame_from_buf(&written);
+        assert!(frame.is_ack());
+        assert_eq!(frame.data, data.to_vec());
+        // Only the single ACK was written out.
+        assert_eq!(written.len(), sz);
+    }
+
+    /// Tests that ACKs of PINGs sent by the `ClientConnection` are matched to
+    /// the outstanding PINGs, regardless of the order in which they arrive.
+    #[test]
+    fn test_client_conn_match_ping_ack() {
+        let first = [1, 1, 1, 1, 1, 1, 1, 1];
+        let second = [2, 2, 2, 2, 2, 2, 2, 2];
+        let ack_for = |data: [u8; 8]| {
+            let mut ack = PingFrame::new_ack();
+            ack.data = data.to_vec();
+            ack
+        };
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&vec![]),
+            TestSession::new());
+        conn.send_ping(first).unwrap();
+        conn.send_ping(second).unwrap();
+
+        // Not an ACK
+        assert!(!conn.match_ping_ack(&PingFrame::with_data(first)));
+        // Unknown token
+        assert!(!conn.match_ping_ack(&ack_for([3; 8])));
+        // ACKs arriving out of order
+        assert!(conn.match_ping_ack(&ack_for(second)));
+        assert!(conn.match_ping_ack(&ack_for(first)));
+        // Each PING is matched only once.
+        assert!(!conn.match_ping_ack(&ack_for(first)));
+        assert!(!conn.match_ping_ack(&ack_for(second)));
+    }
+
+    /// Tests that small chunks of data are packed into the fewest DATA frames
+    /// allowed by the peer's max frame size, with only the last one ending
+    /// the stream.
+    #[test]
+    fn test_client_conn_coalesce_data() {
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&vec![]),
+            TestSession::new());
+        conn.peer_settings.max_frame_size = 256;
+        let chunks: Vec<Vec<u8>> = (0..5).map(|i| vec![i as u8; 100]).collect();
+        let expected: Vec<u8> = chunks.iter().flat_map(|c| c.iter().cloned()).collect();
+
+        let frames = conn.coalesce_data(1, chunks);
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data.len(), 256);
+        assert_eq!(frames[1].data.len(), 244);
+        assert!(!frames[0].is_set(DataFlag::EndStream));
+        assert!(frames[1].is_set(DataFlag::EndStream));
+        for frame in frames.iter() {
+            assert_eq!(frame.get_stream_id(), 1);
+        }
+        let data: Vec<u8> = frames.iter().flat_map(|f| f.data.iter().cloned()).collect();
+        assert_eq!(data, expected);
+    }
+
+    /// Tests that coalescing no data at all gives a single empty DATA frame
+    /// that ends the stream.
+    #[test]
+    fn test_client_conn_coalesce_data_empty() {
+        let conn = ClientConnection::with_connection(
+            build_http_conn(&vec![]),
+            TestSession::new());
+
+        let frames = conn.coalesce_data(3, vec![vec![], vec![]]);
+
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].data.is_empty());
+        assert!(frames[0].is_set(DataFlag::EndStream));
+    }
+
+    /// Tests that handling a batch of raw frames gives one result per frame,
+    /// with failing frames not preventing the rest from being handled.
+    #[test]
+    fn test_client_conn_handle_frames() {
+        let frames = vec![
+            RawFrame::from_buf(&HeadersFrame::new(vec![], 1).serialize()).unwrap(),
+            // DATA on stream 0
+            RawFrame::with_payload((1, 0x0, 0, 0), vec![1]),
+            // An unknown frame type
+            RawFrame::with_payload((1, 0xA, 0, 1), vec![1]),
+            // A length that does not match the payload
+            RawFrame::with_payload((3, 0x0, 0, 1), vec![1]),
+            RawFrame::from_buf(&{
+                let mut frame = DataFrame::new(1);
+                frame.data = vec![1, 2, 3];
+                frame.set_flag(DataFlag::EndStream);
+                frame.serialize()
+            }).unwrap(),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&vec![]),
+            TestSession::new());
+
+        let results = conn.handle_frames(frames);
+
+        assert_eq!(results.len(), 5);
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].as_ref().err().unwrap(), &HttpError::InvalidFrame);
+        assert!(results[2].is_ok());
+        assert_eq!(results[3].as_ref().err().unwrap(), &HttpError::FrameSizeError);
+        assert!(results[4].is_ok());
+        // Only the valid HEADERS and DATA made it to the session.
+        assert_eq!(conn.session.curr_header, 1);
+        assert_eq!(conn.session.curr_chunk, 1);
+    }
+
+    /// Tests that a frame whose header advertises a payload length different
+    /// from its actual payload is rejected with a frame size error before it
+    /// is handled in any way.
+    #[test]
+    fn test_client_conn_inconsistent_frame_length() {
+        let frames = vec![
+            // One byte more than there is...
+            RawFrame::with_payload((5, 0x0, 0x1, 1), b"asdf".to_vec()),
+            // ...one byte less...
+            RawFrame::with_payload((3, 0x0, 0x1, 1), b"asdf".to_vec()),
+            // ...and the same regardless of the frame type.
+            RawFrame::with_payload((1, 0xA, 0, 1), vec![]),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&vec![]),
+            DefaultSession::<DefaultStream>::new());
+        conn.session.new_stream(1);
+
+        let results = conn.handle_frames(frames);
+
+        for res in results.iter() {
+            assert_eq!(res.as_ref().err().unwrap(), &HttpError::FrameSizeError);
+        }
+        // The stream was not touched.
+        let stream = conn.session.get_stream(1).unwrap();
+        assert!(stream.body.is_empty());
+        assert!(!stream.is_closed());
+    }
+
+    /// Tests that frames handled in a batch are recorded in the frame log,
+    /// including the ones that fail to be decoded.
+    #[test]
+    fn test_client_conn_handle_frames_frame_log() {
+        let headers = HeadersFrame::new(vec![], 1).get_header();
+        let data = DataFrame::new(1).get_header();
+        let invalid = (1, 0x0, 0, 0);
+        let frames = vec![
+            RawFrame::from_buf(&HeadersFrame::new(vec![], 1).serialize()).unwrap(),
+            RawFrame::with_payload(invalid, vec![1]),
+            RawFrame::from_buf(&DataFrame::new(1).serialize()).unwrap(),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&vec![]),
+            TestSession::new());
+        conn.enable_frame_log();
+
+        conn.handle_frames(frames);
+
+        let expected = vec![(true, headers), (true, invalid), (true, data)];
+        assert_eq!(conn.frame_log(), &expected[..]);
+    }
+
+    /// Tests that the frame log records the headers of sent and received
+    /// frames in order, only once it is enabled.
+    #[test]
+    fn test_client_conn_frame_log() {
+        let frames: Vec<HttpFrame> = vec![
+            HttpFrame::PingFrame(PingFrame::with_data([1; 8])),
+            HttpFrame::SettingsFrame(SettingsFrame::new()),
+        ];
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&build_stub_from_frames(&frames)),
+            TestSession::new());
+        // Nothing is recorded until the log is enabled.
+        conn.send_ping([0; 8]).unwrap();
+        assert!(conn.frame_log().is_empty());
+
+        conn.enable_frame_log();
+        conn.handle_next_frame().unwrap();
+        conn.handle_next_frame().unwrap();
+
+        // Each received frame is followed by the ACK sent in response to it.
+        let ping_ack = {
+            let mut ack = PingFrame::new_ack();
+            ack.data = vec![1; 8];
+            ack
+        };
+        let expected = vec![
+            (true, PingFrame::with_data([1; 8]).get_header()),
+            (false, ping_ack.get_header()),
+            (true, SettingsFrame::new().get_header()),
+            (false, SettingsFrame::new_ack().get_header()),
+        ];
+        assert_eq!(conn.frame_log(), &expected[..]);
+    }
+
+    /// Tests that a SETTINGS ACK that carries a payload is rejected by the
+    /// `ClientConnection` with a frame size error and does not count as an
+    /// ACK.
+    #[test]
+    fn test_client_conn_settings_ack_with_payload() {
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&{
+                // An ACK carrying an EnablePush(0) setting
+                let payload = vec![0, 2, 0, 0, 0, 0];
+                let mut buf: Vec<u8> = Vec::new();
+                buf.extend(pack_header(&(payload.len() as u32, 0x4, 0x1, 0)).to_vec().into_iter());
+                buf.extend(payload.into_iter());
+                buf
+            }),
+            TestSession::new());
+
+        assert_eq!(conn.handle_next_frame().err().unwrap(), HttpError::FrameSizeError);
+        assert!(!conn.local_settings_acked);
+    }
+
+    /// Tests that a SETTINGS frame whose payload length is not a multiple of
+    /// 6 is rejected by the `ClientConnection` with a frame size error,
+    /// without being acknowledged.
+    #[test]
+    fn test_client_conn_settings_invalid_length() {
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&{
+                let payload = vec![0, 2, 0, 0, 0, 0, 0];
+                let mut buf: Vec<u8> = Vec::new();
+                buf.extend(pack_header(&(payload.len() as u32, 0x4, 0, 0)).to_vec().into_iter());
+                buf.extend(payload.into_iter());
+                buf
+            }),
+            TestSession::new());
+
+        assert_eq!(conn.handle_next_frame().err().unwrap(), HttpError::FrameSizeError);
+        assert!(!conn.peer_settings_received);
+        assert!(conn.conn.stream.get_written().is_empty());
+    }
+
+    /// Tests that a DATA frame on stream 0 fails the whole connection: the
+    /// error is returned from `handle_next_frame` and the session is never
+    /// notified of the data.
+    #[test]
+    fn test_client_conn_data_frame_on_stream_zero() {
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&{
+                let mut buf: Vec<u8> = Vec::new();
+                buf.extend(pack_header(&(4, 0x0, 0, 0)).to_vec().into_iter());
+                buf.extend(b"asdf".to_vec().into_iter());
+                buf
+            }),
+            TestSession::new());
+
+        assert_eq!(conn.handle_next_frame().err().unwrap(), HttpError::InvalidFrame);
+        assert_eq!(conn.session.curr_chunk, 0);
+    }
+
+    /// Tests that the reserved bit of a received frame's stream ID is ignored,
+    /// so that the frame is handled as belonging to the 31-bit stream ID,
+    /// while a stream ID that is 0 once the bit is cleared is still invalid
+    /// for a DATA frame.
+    #[test]
+    fn test_client_conn_stream_id_reserved_bit() {
+        let data_frame = |stream_id: u32| {
+            let mut buf: Vec<u8> = Vec::new();
+            buf.extend(pack_header(&(4, 0x0, 0x1, 0)).to_vec().into_iter());
+            // Overwrite the stream ID octets directly, as `pack_header` never
+            // sets the reserved bit.
+            buf[5] = (stream_id >> 24) as u8;
+            buf[6] = (stream_id >> 16) as u8;
+            buf[7] = (stream_id >> 8) as u8;
+            buf[8] = stream_id as u8;
+            buf.extend(b"asdf".to_vec().into_iter());
+            buf
+        };
+        {
+            let mut conn = ClientConnection::with_connection(
+                build_http_conn(&data_frame(0x80000001)),
+                DefaultSession::<DefaultStream>::new());
+            conn.session.new_stream(1);
+
+            conn.handle_next_frame().ok().unwrap();
+
+            let stream = conn.session.get_stream(1).unwrap();
+            assert_eq!(stream.body, b"asdf".to_vec());
+            assert!(stream.is_closed());
+        }
+        {
+            let mut conn = ClientConnection::with_connection(
+                build_http_conn(&data_frame(0x80000000)),
+                TestSession::new());
+
+            assert_eq!(conn.handle_next_frame().err().unwrap(), HttpError::InvalidFrame);
+            assert_eq!(conn.session.curr_chunk, 0);
+        }
+    }
+
+    /// Tests that a DATA frame whose padding length exceeds the length of its
+    /// payload is rejected by the `ClientConnection` without notifying the
+    /// session.
+    #[test]
+    fn test_client_conn_data_frame_invalid_padding() {
+        let mut conn = ClientConnection::with_connection(
+            build_http_conn(&{
+                // The pad length (5) is larger than what remains of the
+                // payload (4 bytes).
+                let payload = vec![5, b'a', b's', b'd', b'f'];
+                let mut buf: Vec<u8> = Vec::new();
+                buf.extend(pack_header(&(payload.len() as u32, 0, 0x8, 1)).to_vec().into_iter());
+                buf.extend(payload.into_iter());
+                buf
+            }),
+            TestSession::new());
+
+        assert_eq!(
+            conn.handle_next_frame().err().unwrap(),
+            HttpError::InvalidFrame);
+        assert_eq!(conn.session.curr_chunk, 0);
+    }
 }
@@ -121,7 +121,7 @@ impl Frame for DataFrame {
     /// constructed from the given `RawFrame`.
     fn from_raw(raw_frame: RawFrame) -> Option<DataFrame> {
         // Unpack the header
-        let (len, frame_type, flags, stream_id) = raw_frame.header;
+        let (_, frame_type, flags, stream_id) = raw_frame.header;
         // Check that the frame type is correct for this frame implementation
         if frame_type != 0x0 {
             return None;
@@ -129,7 +129,7 @@ impl Frame for DataFrame {
         // Check that the length given in the header matches the payload
         // length; if not, something went wrong and we do not consider this a
         // valid frame.
-        if (len as usize) != raw_frame.payload.len() {
+        if !raw_frame.is_consistent() {
             return None;
         }
         // A DATA frame cannot be associated to the connection itself.
@@ -226,6 +226,21 @@ mod tests {
         assert_eq!(frame.get_header(), header);
     }
 
+    /// Tests that a DATA frame whose header advertises a payload length
+    /// different from the actual payload is not decoded.
+    #[test]
+    fn test_data_frame_parse_inconsistent_length() {
+        let payload = b"asdf".to_vec();
+        // The header claims one byte more than there is
+        let header = (payload.len() as u32 + 1, 0u8, 0u8, 1u32);
+        let raw = RawFrame::with_payload(header, payload);
+        assert!(!raw.is_consistent());
+
+        let frame: Option<DataFrame> = Frame::from_raw(raw);
+
+        assert!(frame.is_none());
+    }
+
     /// Tests that the `DataFrame` struct correctly interprets a DATA frame
     /// with a number of padding bytes set.
     #[test]
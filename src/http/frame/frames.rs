@@ -1,4 +1,6 @@
 use std::mem;
+use std::cmp;
+use std::fmt;
 use super::super::StreamId;
 
 /// An alias for the 9-byte buffer that each HTTP/2 frame header must be stored
@@ -8,11 +10,53 @@ pub type FrameHeaderBuffer = [u8; 9];
 /// header.
 pub type FrameHeader = (u32, u8, u8, u32);
 
+/// The components of an HTTP/2 frame header with named fields.
+///
+/// It carries exactly the same information as the `FrameHeader` tuple and
+/// can be freely converted to and from it, but avoids having to remember
+/// which tuple index holds which component.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Copy)]
+pub struct ParsedHeader {
+    /// The length of the frame's payload
+    pub length: u32,
+    /// The (raw) type of the frame
+    pub frame_type: u8,
+    /// The flags of the frame, packed into a single octet
+    pub flags: u8,
+    /// The ID of the stream with which the frame is associated
+    pub stream_id: StreamId,
+}
+
+impl From<FrameHeader> for ParsedHeader {
+    fn from(header: FrameHeader) -> ParsedHeader {
+        let (length, frame_type, flags, stream_id) = header;
+        ParsedHeader {
+            length: length,
+            frame_type: frame_type,
+            flags: flags,
+            stream_id: stream_id,
+        }
+    }
+}
+
+impl From<ParsedHeader> for FrameHeader {
+    fn from(header: ParsedHeader) -> FrameHeader {
+        (header.length, header.frame_type, header.flags, header.stream_id)
+    }
+}
+
 /// Deconstructs a `FrameHeader` into its corresponding 4 components,
 /// represented as a 4-tuple: `(length, frame_type, flags, stream_id)`.
 ///
 /// The frame `type` and `flags` components are returned as their original
 /// octet representation, rather than reinterpreted.
+///
+/// The reserved bit in front of the stream identifier is ignored, as the
+/// spec requires (section 4.1.), so the returned stream ID is always the
+/// 31-bit value.
 pub fn unpack_header(header: &FrameHeaderBuffer) -> FrameHeader {
     let length: u32 =
         ((header[0] as u32) << 16) |
@@ -20,26 +64,64 @@ pub fn unpack_header(header: &FrameHeaderBuffer) -> FrameHeader {
         ((header[2] as u32) <<  0);
     let frame_type = header[3];
     let flags = header[4];
-    let stream_id: u32 = unpack_octets_4!(header, 5, u32);
+    // Clear the reserved bit
+    let stream_id: u32 = unpack_octets_4!(header, 5, u32) & !(1 << 31);
 
     (length, frame_type, flags, stream_id)
 }
 
 /// Constructs a buffer of 9 bytes that represents the given `FrameHeader`.
 pub fn pack_header(header: &FrameHeader) -> FrameHeaderBuffer {
+    let mut buf = [0; 9];
+    pack_header_into(&mut buf, header);
+
+    buf
+}
+
+/// Writes the 9 bytes that represent the given `FrameHeader` into the first
+/// 9 bytes of the given buffer.
+///
+/// The reserved bit in front of the stream identifier is always written as
+/// 0 (section 4.1.), even if it is set in the given stream ID.
+///
+/// This allows the header to be serialized directly into a caller-provided
+/// buffer (e.g. the one that will hold the entire serialized frame), instead
+/// of allocating a new array for each header.
+///
+/// # Panics
+///
+/// If the given buffer is shorter than 9 bytes, the function will panic.
+pub fn pack_header_into(buf: &mut [u8], header: &FrameHeader) {
     let &(length, frame_type, flags, stream_id) = header;
+    // The reserved bit MUST remain unset when sending
+    let stream_id = stream_id & 0x7FFFFFFF;
+
+    buf[0] = ((length >> 16) & 0x000000FF) as u8;
+    buf[1] = ((length >>  8) & 0x000000FF) as u8;
+    buf[2] = ((length >>  0) & 0x000000FF) as u8;
+    buf[3] = frame_type;
+    buf[4] = flags;
+    buf[5] = ((stream_id >> 24) & 0x000000FF) as u8;
+    buf[6] = ((stream_id >> 16) & 0x000000FF) as u8;
+    buf[7] = ((stream_id >>  8) & 0x000000FF) as u8;
+    buf[8] = ((stream_id >>  0) & 0x000000FF) as u8;
+}
 
-    [
-        (((length >> 16) & 0x000000FF) as u8),
-        (((length >>  8) & 0x000000FF) as u8),
-        (((length >>  0) & 0x000000FF) as u8),
-        frame_type,
-        flags,
-        (((stream_id >> 24) & 0x000000FF) as u8),
-        (((stream_id >> 16) & 0x000000FF) as u8),
-        (((stream_id >>  8) & 0x000000FF) as u8),
-        (((stream_id >>  0) & 0x000000FF) as u8),
-    ]
+/// Returns whether frames of the given type are subject to flow control.
+///
+/// As per section 6.9. of the HTTP/2 spec, only DATA frames are subject to
+/// flow control; all other frame types are not.
+pub fn is_flow_controlled(frame_type: u8) -> bool {
+    frame_type == 0x0
+}
+
+/// Returns whether frames of the given type can open a new stream.
+///
+/// As per section 5.1. of the HTTP/2 spec, a stream leaves the idle state
+/// only by a HEADERS frame being sent or received on it, or by being
+/// reserved by a PUSH_PROMISE frame.
+pub fn opens_stream(frame_type: u8) -> bool {
+    frame_type == 0x1 || frame_type == 0x5
 }
 
 /// A helper function that parses the given payload, considering it padded.
@@ -73,12 +155,50 @@ pub fn parse_padded_payload<'a>(payload: &'a [u8]) -> Option<(&'a [u8], u8)> {
     Some((&payload[1..payload.len() - pad_len], pad_len as u8))
 }
 
+/// A helper function that parses the 5-byte stream dependency structure
+/// found at the start of the given buffer, as used by PRIORITY frames and
+/// HEADERS frames with the PRIORITY flag set (HTTP/2 spec, section 6.2.).
+///
+/// # Returns
+///
+/// A 3-tuple of the ID of the stream that is depended on (with the exclusive
+/// bit cleared), whether the dependency is exclusive, and the weight.
+///
+/// If the buffer is shorter than 5 bytes, returns `None`.
+pub fn parse_priority_fields(buf: &[u8]) -> Option<(StreamId, bool, u8)> {
+    if buf.len() < 5 {
+        return None;
+    }
+    // The most significant bit of the first byte is the "E" bit indicating
+    // whether the dependency is exclusive.
+    let is_exclusive = buf[0] & 0x80 != 0;
+    // The stream ID is only 31 bits, so the "E" bit is cleared.
+    let stream_id = unpack_octets_4!(buf, 0, u32) & !(1 << 31);
+
+    Some((stream_id, is_exclusive, buf[4]))
+}
+
 /// A trait that all HTTP/2 frame header flags need to implement.
 pub trait Flag {
     /// Returns a bit mask that represents the flag.
     fn bitmask(&self) -> u8;
 }
 
+/// A flag type for frames that do not define any flags. Its bitmask is
+/// always empty.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Copy)]
+pub struct NoFlag;
+
+impl Flag for NoFlag {
+    #[inline]
+    fn bitmask(&self) -> u8 {
+        0
+    }
+}
+
 /// A trait that all HTTP/2 frame structs need to implement.
 pub trait Frame {
     /// The type that represents the flags that the particular `Frame` can take.
@@ -146,6 +266,22 @@ impl RawFrame {
         }
     }
 
+    /// Checks whether the payload length advertised in the frame's header
+    /// matches the length of the payload that the frame actually carries.
+    ///
+    /// A frame for which this does not hold cannot be decoded into any
+    /// `Frame` implementation.
+    #[inline]
+    pub fn is_consistent(&self) -> bool {
+        self.parsed_header().length as usize == self.payload.len()
+    }
+
+    /// Returns the header of the frame as a `ParsedHeader`.
+    #[inline]
+    pub fn parsed_header(&self) -> ParsedHeader {
+        ParsedHeader::from(self.header)
+    }
+
     /// Creates a new `RawFrame` by parsing the given buffer.
     ///
     /// # Returns
@@ -159,36 +295,158 @@ impl RawFrame {
     /// contain enough data to contain the entire payload (whose length was
     /// advertised in the header).
     pub fn from_buf(buf: &[u8]) -> Option<RawFrame> {
+        RawFrame::from_buf_ref(buf).map(|raw| raw.into())
+    }
+
+    /// Creates a new `RawFrameRef` by parsing the given buffer. Unlike the
+    /// `from_buf` method, the payload is not copied out of the buffer, but
+    /// the returned frame only borrows the appropriate slice of it.
+    ///
+    /// # Returns
+    ///
+    /// The same rules as for the `from_buf` method apply: if the buffer does
+    /// not contain an entire frame (both the header and the full payload),
+    /// `None` is returned.
+    pub fn from_buf_ref<'a>(buf: &'a [u8]) -> Option<RawFrameRef<'a>> {
         if buf.len() < 9 {
             return None;
         }
-        let header = unpack_header(unsafe {
+        let header = ParsedHeader::from(unpack_header(unsafe {
             assert!(buf.len() >= 9);
             // We just asserted that this transmute is safe.
             mem::transmute(buf.as_ptr())
-        });
-        let payload_len = header.0 as usize;
+        }));
+        let payload_len = header.length as usize;
 
         if buf[9..].len() < payload_len {
             return None;
         }
 
-        Some(RawFrame {
-            header: header,
-            payload: buf[9..9 + header.0 as usize].to_vec(),
+        Some(RawFrameRef {
+            header: header.into(),
+            payload: &buf[9..9 + payload_len],
         })
     }
 }
 
+/// The `Debug` implementation for the `RawFrame` shows the header and the
+/// length of the payload, but only a short hex preview of the payload itself
+/// (its first 16 bytes), so that logging large frames stays readable.
+impl fmt::Debug for RawFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let preview_len = cmp::min(self.payload.len(), 16);
+        try!(write!(f, "RawFrame {{ header: {:?}, payload_len: {}, payload: [",
+                    self.header, self.payload.len()));
+        for (i, byte) in self.payload[..preview_len].iter().enumerate() {
+            if i > 0 {
+                try!(write!(f, " "));
+            }
+            try!(write!(f, "{:02x}", byte));
+        }
+        if self.payload.len() > preview_len {
+            try!(write!(f, " ..."));
+        }
+        write!(f, "] }}")
+    }
+}
+
+/// A struct that represents a raw HTTP/2 frame whose payload is borrowed from
+/// some underlying buffer, instead of being owned by the frame.
+///
+/// This makes it possible for a decoder that works off of a single read
+/// buffer to parse frames without allocating a new payload buffer for each
+/// of them. If an owned `RawFrame` is required, the `RawFrameRef` can be
+/// converted into one (which copies the payload).
+pub struct RawFrameRef<'a> {
+    /// The parsed header of the frame.
+    pub header: FrameHeader,
+    /// The payload of the frame, as a slice of the buffer that the frame was
+    /// parsed from.
+    pub payload: &'a [u8],
+}
+
+impl<'a> From<RawFrameRef<'a>> for RawFrame {
+    fn from(raw: RawFrameRef<'a>) -> RawFrame {
+        RawFrame::with_payload(raw.header, raw.payload.to_vec())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
         unpack_header,
         pack_header,
+        pack_header_into,
+        is_flow_controlled,
+        opens_stream,
+        parse_priority_fields,
         RawFrame,
+        FrameHeader,
+        ParsedHeader,
     };
 
 
+    /// Tests that the stream dependency fields are correctly extracted from a
+    /// buffer of 5 or more bytes.
+    #[test]
+    fn test_parse_priority_fields() {
+        // Exclusive dependency on stream 3, weight 10
+        assert_eq!(
+            parse_priority_fields(&[0x80, 0, 0, 3, 10]),
+            Some((3, true, 10)));
+        // Non-exclusive, with the largest stream ID; trailing bytes ignored
+        assert_eq!(
+            parse_priority_fields(&[0x7F, 0xFF, 0xFF, 0xFF, 255, 1, 2]),
+            Some((0x7FFFFFFF, false, 255)));
+    }
+
+    /// Tests that a buffer too short to hold the stream dependency fields
+    /// gives `None`.
+    #[test]
+    fn test_parse_priority_fields_too_short() {
+        assert_eq!(parse_priority_fields(&[]), None);
+        assert_eq!(parse_priority_fields(&[0x80, 0, 0, 3]), None);
+    }
+
+    /// Tests that a `RawFrame` is only consistent when the length in its
+    /// header matches the length of its payload.
+    #[test]
+    fn test_raw_frame_is_consistent() {
+        assert!(RawFrame::new((0, 0x4, 0, 0)).is_consistent());
+        assert!(RawFrame::with_payload((3, 0x0, 0, 1), vec![1, 2, 3]).is_consistent());
+        assert!(!RawFrame::with_payload((4, 0x0, 0, 1), vec![1, 2, 3]).is_consistent());
+        assert!(!RawFrame::with_payload((2, 0x0, 0, 1), vec![1, 2, 3]).is_consistent());
+    }
+
+    /// Tests that `RawFrame::parsed_header` gives the named view of the
+    /// frame's header.
+    #[test]
+    fn test_raw_frame_parsed_header() {
+        let raw = RawFrame::with_payload((3, 0x0, 0x1, 5), vec![1, 2, 3]);
+
+        let header = raw.parsed_header();
+
+        assert_eq!(header, ParsedHeader::from(raw.header));
+        assert_eq!(header.length, 3);
+        assert_eq!(header.stream_id, 5);
+    }
+
+    /// Tests that a `FrameHeader` converts into a `ParsedHeader` with the
+    /// matching named components and back into the same tuple.
+    #[test]
+    fn test_parsed_header_round_trip() {
+        let header: FrameHeader = (0x123456, 0x1, 0x5, 0x7FFFFFFF);
+
+        let parsed = ParsedHeader::from(header);
+
+        assert_eq!(parsed.length, 0x123456);
+        assert_eq!(parsed.frame_type, 0x1);
+        assert_eq!(parsed.flags, 0x5);
+        assert_eq!(parsed.stream_id, 0x7FFFFFFF);
+        let back: FrameHeader = parsed.into();
+        assert_eq!(back, header);
+    }
+
     /// Tests that the `unpack_header` function correctly returns the
     /// components of HTTP/2 frame headers.
     #[test]
@@ -231,6 +489,15 @@ mod tests {
                 ((1 << 24) - 1, 0, 0, 1 + (1 << 8) + (1 << 16) + (1 << 24)),
                 unpack_header(&header));
         }
+        {
+            // The reserved bit of the stream ID is ignored.
+            let header = [0, 0, 0, 0, 0, 0x80, 0, 0, 1];
+            assert_eq!((0, 0, 0, 1), unpack_header(&header));
+            let header = [0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+            assert_eq!((0, 0, 0, (1 << 31) - 1), unpack_header(&header));
+            let header = [0, 0, 0, 0, 0, 0x80, 0, 0, 0];
+            assert_eq!((0, 0, 0, 0), unpack_header(&header));
+        }
     }
 
     /// Tests that the `pack_header` function correctly returns the buffer
@@ -278,6 +545,83 @@ mod tests {
         }
     }
 
+    /// Tests that the `pack_header_into` function writes exactly the same
+    /// bytes as the ones returned by the `pack_header` function.
+    #[test]
+    fn test_pack_header_into() {
+        let headers = vec![
+            (0, 0, 0, 0),
+            (1, 2, 3, 4),
+            (1, 200, 100, 4),
+            (256 * 256, 0, 0, 0),
+            ((1 << 24) - 1, 0, 0, 1 + (1 << 8) + (1 << 16) + (1 << 24)),
+        ];
+        for header in headers.iter() {
+            let mut buf = [0; 9];
+
+            pack_header_into(&mut buf, header);
+
+            assert_eq!(buf, pack_header(header));
+        }
+        // Only the first 9 bytes of a larger buffer are written.
+        {
+            let header = (1, 2, 3, 4);
+            let mut buf = [0xFF; 12];
+
+            pack_header_into(&mut buf, &header);
+
+            assert_eq!(&buf[..9], &pack_header(&header)[..]);
+            assert_eq!(&buf[9..], &[0xFF, 0xFF, 0xFF]);
+        }
+    }
+
+    /// Tests that the reserved bit of the stream ID is never sent, so that
+    /// packing a header and unpacking it again gives the 31-bit stream ID.
+    #[test]
+    fn test_pack_header_reserved_bit() {
+        let buf = pack_header(&(1, 0x0, 0, (1 << 31) | 5));
+        assert_eq!(&buf[5..], &[0, 0, 0, 5]);
+        assert_eq!(unpack_header(&buf), (1, 0x0, 0, 5));
+
+        let buf = pack_header(&(0, 0x4, 0, 0xFFFFFFFF));
+        assert_eq!(&buf[5..], &[0x7F, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(unpack_header(&buf), (0, 0x4, 0, 0x7FFFFFFF));
+
+        // Any header with a 31-bit stream ID survives the round trip intact.
+        let header = ((1 << 24) - 1, 0x1, 0x25, (1 << 31) - 1);
+        assert_eq!(unpack_header(&pack_header(&header)), header);
+    }
+
+    /// Tests that the `is_flow_controlled` function only considers DATA
+    /// frames to be flow controlled.
+    #[test]
+    fn test_is_flow_controlled() {
+        // DATA
+        assert!(is_flow_controlled(0x0));
+        // HEADERS, PRIORITY, RST_STREAM, SETTINGS, PUSH_PROMISE, PING,
+        // GOAWAY, WINDOW_UPDATE, CONTINUATION
+        for frame_type in 0x1..0xA {
+            assert!(!is_flow_controlled(frame_type));
+        }
+        // Unknown frame types
+        assert!(!is_flow_controlled(0xA));
+        assert!(!is_flow_controlled(0xFF));
+    }
+
+    /// Tests that the `opens_stream` function only considers HEADERS and
+    /// PUSH_PROMISE frames to open streams.
+    #[test]
+    fn test_opens_stream() {
+        for frame_type in 0x0..0xA {
+            // HEADERS and PUSH_PROMISE
+            let expected = frame_type == 0x1 || frame_type == 0x5;
+            assert_eq!(opens_stream(frame_type), expected);
+        }
+        // Unknown frame types
+        assert!(!opens_stream(0xA));
+        assert!(!opens_stream(0xFF));
+    }
+
     /// Tests that the `RawFrame::from_buf` method correctly constructs a
     /// `RawFrame` from a given buffer.
     #[test]
@@ -344,4 +688,92 @@ mod tests {
             assert!(RawFrame::from_buf(&[]).is_none());
         }
     }
+
+    /// Tests that the `RawFrame::from_buf_ref` method correctly constructs a
+    /// `RawFrameRef` from a given buffer, without copying the payload.
+    #[test]
+    fn test_raw_frame_ref_from_buffer() {
+        // Correct frame with trailing data
+        {
+            let data = b"123";
+            let header = (data.len() as u32, 0x1, 0, 1);
+            let buf = {
+                let mut buf = Vec::new();
+                buf.extend(pack_header(&header).to_vec().into_iter());
+                buf.extend(data.to_vec().into_iter());
+                buf.extend(vec![1, 2, 3, 4, 5].into_iter());
+                buf
+            };
+
+            let raw = RawFrame::from_buf_ref(&buf).unwrap();
+
+            assert_eq!(raw.header, header);
+            assert_eq!(raw.payload, data);
+            // The payload points directly into the original buffer, i.e. it
+            // was not copied into a newly allocated one.
+            assert_eq!(raw.payload.as_ptr(), buf[9..].as_ptr());
+        }
+        // Missing payload chunk
+        {
+            let data = b"123";
+            let header = (data.len() as u32, 0x1, 0, 1);
+            let buf = {
+                let mut buf = Vec::new();
+                buf.extend(pack_header(&header).to_vec().into_iter());
+                buf.extend(data[..2].to_vec().into_iter());
+                buf
+            };
+
+            assert!(RawFrame::from_buf_ref(&buf).is_none());
+        }
+        // Completely empty buffer
+        {
+            assert!(RawFrame::from_buf_ref(&[]).is_none());
+        }
+    }
+
+    /// Tests that a `RawFrameRef` can be converted into an owned `RawFrame`.
+    #[test]
+    fn test_raw_frame_ref_into_raw_frame() {
+        let data = b"123";
+        let header = (data.len() as u32, 0x1, 0, 1);
+        let buf = {
+            let mut buf = Vec::new();
+            buf.extend(pack_header(&header).to_vec().into_iter());
+            buf.extend(data.to_vec().into_iter());
+            buf
+        };
+
+        let raw: RawFrame = RawFrame::from_buf_ref(&buf).unwrap().into();
+
+        assert_eq!(raw.header, header);
+        assert_eq!(raw.payload, data);
+    }
+
+    /// Tests that the `Debug` representation of a `RawFrame` contains the
+    /// header and the payload length, but only a preview of the payload.
+    #[test]
+    fn test_raw_frame_debug() {
+        {
+            let raw = RawFrame::with_payload((3, 0x1, 0, 5), b"123".to_vec());
+
+            let debug = format!("{:?}", raw);
+
+            assert_eq!(
+                debug,
+                "RawFrame { header: (3, 1, 0, 5), payload_len: 3, payload: [31 32 33] }");
+        }
+        {
+            let raw = RawFrame::with_payload((1000, 0x0, 0, 7), vec![0xAB; 1000]);
+
+            let debug = format!("{:?}", raw);
+
+            // The stream ID and the full payload length are found...
+            assert!(debug.contains("(1000, 0, 0, 7)"));
+            assert!(debug.contains("payload_len: 1000"));
+            // ...but only the first 16 bytes of the payload are dumped.
+            assert_eq!(debug.split("ab").count() - 1, 16);
+            assert!(debug.contains("..."));
+        }
+    }
 }
@@ -3,6 +3,7 @@ use super::frames::{
     Frame,
     Flag,
     parse_padded_payload,
+    parse_priority_fields,
     pack_header,
     RawFrame,
     FrameHeader
@@ -67,22 +68,19 @@ impl StreamDependency {
     ///
     /// If the given buffer has less than 5 elements, the method will panic.
     pub fn parse(buf: &[u8]) -> StreamDependency {
-        // The most significant bit of the first byte is the "E" bit indicating
-        // whether the dependency is exclusive.
-        let is_exclusive = buf[0] & 0x80 != 0;
-        let stream_id = {
-            // Parse the first 4 bytes into a u32...
-            let mut id = unpack_octets_4!(buf, 0, u32);
-            // ...clear the first bit since the stream id is only 31 bits.
-            id &= !(1 << 31);
-            id
-        };
+        StreamDependency::try_parse(buf).unwrap()
+    }
 
-        StreamDependency {
-            stream_id: stream_id,
-            weight: buf[4],
-            is_exclusive: is_exclusive,
-        }
+    /// Parses the first 5 bytes in the buffer as a `StreamDependency`, if
+    /// the buffer is long enough.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the given buffer has less than 5 elements.
+    pub fn try_parse(buf: &[u8]) -> Option<StreamDependency> {
+        parse_priority_fields(buf).map(|(stream_id, is_exclusive, weight)| {
+            StreamDependency::new(stream_id, weight, is_exclusive)
+        })
     }
 
     /// Serializes the `StreamDependency` into a 5-byte buffer representing the
@@ -161,6 +159,22 @@ impl HeadersFrame {
         }
     }
 
+    /// Creates a new `HeadersFrame` that carries the entire given header block
+    /// of a request on the given stream, i.e. with the END_HEADERS flag set.
+    ///
+    /// If `end_stream` is set, the frame also has the END_STREAM flag set,
+    /// meaning that the request has no body.
+    pub fn request(stream_id: StreamId, header_block: Vec<u8>, end_stream: bool)
+            -> HeadersFrame {
+        let mut frame = HeadersFrame::new(header_block, stream_id);
+        frame.set_flag(HeadersFlag::EndHeaders);
+        if end_stream {
+            frame.set_flag(HeadersFlag::EndStream);
+        }
+
+        frame
+    }
+
     /// Returns whether this frame ends the headers. If not, there MUST be a
     /// number of follow up CONTINUATION frames that send the rest of the
     /// header data.
@@ -214,7 +228,7 @@ impl Frame for HeadersFrame {
     /// Otherwise, returns a newly constructed `HeadersFrame`.
     fn from_raw(raw_frame: RawFrame) -> Option<HeadersFrame> {
         // Unpack the header
-        let (len, frame_type, flags, stream_id) = raw_frame.header;
+        let (_, frame_type, flags, stream_id) = raw_frame.header;
         // Check that the frame type is correct for this frame implementation
         if frame_type != 0x1 {
             return None;
@@ -222,7 +236,7 @@ impl Frame for HeadersFrame {
         // Check that the length given in the header matches the payload
         // length; if not, something went wrong and we do not consider this a
         // valid frame.
-        if (len as usize) != raw_frame.payload.len() {
+        if !raw_frame.is_consistent() {
             return None;
         }
         // Check that the HEADERS frame is not associated to stream 0
@@ -243,10 +257,19 @@ impl Frame for HeadersFrame {
         };
 
         // From the actual payload we extract the stream dependency info, if
-        // the appropriate flag is set.
+        // the appropriate flag is set. The 5 bytes of the dependency info
+        // come right after the padding length (if any) and before the header
+        // fragment.
         let priority = (flags & HeadersFlag::Priority.bitmask()) != 0;
         let (data, stream_dep) = if priority {
-            (&actual[5..], Some(StreamDependency::parse(&actual[..5])))
+            match StreamDependency::try_parse(actual) {
+                // A stream cannot depend on itself (section 5.3.1.).
+                Some(ref dep) if dep.stream_id == stream_id => return None,
+                Some(dep) => (&actual[5..], Some(dep)),
+                // The payload (once the padding is removed) is too short to
+                // even contain the stream dependency.
+                None => return None,
+            }
         } else {
             (actual, None)
         };
@@ -339,6 +362,61 @@ mod tests {
         assert!(frame.padding_len.is_none());
     }
 
+    /// Tests that a HEADERS frame built for a request carries the whole header
+    /// block and survives being serialized and parsed back.
+    #[test]
+    fn test_headers_frame_request() {
+        let block = b"123".to_vec();
+        {
+            let frame = HeadersFrame::request(1, block.clone(), true);
+            let serialized = frame.serialize();
+
+            let parsed = build_test_frame::<HeadersFrame>(
+                &(block.len() as u32, 0x1, 0x1 | 0x4, 1), &serialized[9..]);
+
+            assert_eq!(parsed, frame);
+            assert!(parsed.is_headers_end());
+            assert!(parsed.is_end_of_stream());
+            assert_eq!(parsed.header_fragment, block);
+        }
+        {
+            let frame = HeadersFrame::request(3, block.clone(), false);
+            let serialized = frame.serialize();
+
+            let parsed = build_test_frame::<HeadersFrame>(
+                &(block.len() as u32, 0x1, 0x4, 3), &serialized[9..]);
+
+            assert_eq!(parsed, frame);
+            assert!(parsed.is_headers_end());
+            assert!(!parsed.is_end_of_stream());
+            assert!(parsed.stream_dep.is_none());
+            assert!(parsed.padding_len.is_none());
+        }
+    }
+
+    /// Tests that a HEADERS frame whose priority information makes the stream
+    /// depend on itself is not decoded.
+    #[test]
+    fn test_headers_frame_parse_self_dependency() {
+        let dep = StreamDependency::new(3, 10, false);
+        let payload = {
+            let mut buf: Vec<u8> = Vec::new();
+            buf.extend(dep.serialize().to_vec().into_iter());
+            buf.extend(b"123".to_vec().into_iter());
+            buf
+        };
+        let header = (payload.len() as u32, 0x1, 0x20, 3);
+
+        let frame: Option<HeadersFrame> = Frame::from_raw(
+            RawFrame::with_payload(header, payload.clone()));
+        assert!(frame.is_none());
+
+        // The same dependency is fine for any other stream.
+        let header = (payload.len() as u32, 0x1, 0x20, 5);
+        let frame = build_test_frame::<HeadersFrame>(&header, &payload);
+        assert_eq!(frame.stream_dep, Some(dep));
+    }
+
     /// Tests that a HEADERS frame with padding is correctly parsed.
     #[test]
     fn test_headers_frame_parse_with_padding() {
@@ -404,6 +482,50 @@ mod tests {
         assert_eq!(frame.padding_len.unwrap(), 4);
     }
 
+    /// Tests that a HEADERS frame with both padding and priority correctly
+    /// extracts each part of the payload from its respective offset, even
+    /// when the header fragment is very short.
+    #[test]
+    fn test_headers_frame_parse_padding_and_priority_short_fragment() {
+        let dep = StreamDependency::new(3, 10, false);
+        // Pad length, followed by the dependency, the fragment and padding.
+        let payload = vec![3, 0, 0, 0, 3, 10, b'a', 0, 0, 0];
+        let header = (payload.len() as u32, 0x1, 0x20 | 0x8, 1);
+
+        let frame = build_test_frame::<HeadersFrame>(&header, &payload);
+
+        assert_eq!(frame.header_fragment, b"a");
+        assert_eq!(frame.stream_dep.unwrap(), dep);
+        assert_eq!(frame.padding_len.unwrap(), 3);
+    }
+
+    /// Tests that a HEADERS frame with the priority flag whose payload is too
+    /// short to contain the stream dependency is considered invalid, instead
+    /// of the priority fields being read from the padding.
+    #[test]
+    fn test_headers_frame_parse_priority_layout_does_not_fit() {
+        {
+            // Padded: after removing the padding, only 3 bytes remain.
+            let payload = build_padded_frame_payload(&[0, 0, 0], 3);
+            let header = (payload.len() as u32, 0x1, 0x20 | 0x8, 1);
+
+            let frame: Option<HeadersFrame> = Frame::from_raw(
+                RawFrame::with_payload(header, payload));
+
+            assert!(frame.is_none());
+        }
+        {
+            // Not padded, but still less than 5 bytes.
+            let payload = vec![0, 0, 0, 1];
+            let header = (payload.len() as u32, 0x1, 0x20, 1);
+
+            let frame: Option<HeadersFrame> = Frame::from_raw(
+                RawFrame::with_payload(header, payload));
+
+            assert!(frame.is_none());
+        }
+    }
+
     /// Tests that a HEADERS with stream ID 0 is considered invalid.
     #[test]
     fn test_headers_frame_parse_invalid_stream_id() {
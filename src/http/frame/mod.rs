@@ -23,11 +23,18 @@ macro_rules! unpack_octets_4 {
 pub use self::frames::{
     Frame,
     Flag,
+    NoFlag,
+    is_flow_controlled,
+    opens_stream,
     parse_padded_payload,
+    parse_priority_fields,
     unpack_header,
     pack_header,
+    pack_header_into,
     RawFrame,
-    FrameHeader
+    RawFrameRef,
+    FrameHeader,
+    ParsedHeader
 };
 pub use self::dataframe::{
     DataFlag,
@@ -47,6 +54,8 @@ pub use self::pingframe::{
     PingFlag,
     PingFrame
 };
+pub use self::windowupdateframe::WindowUpdateFrame;
+pub use self::rststreamframe::RstStreamFrame;
 
 pub mod frames;
 mod test;
@@ -54,3 +63,5 @@ pub mod dataframe;
 pub mod settingsframe;
 pub mod headersframe;
 pub mod pingframe;
+pub mod windowupdateframe;
+pub mod rststreamframe;
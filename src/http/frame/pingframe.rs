@@ -51,6 +51,17 @@ impl PingFrame {
         }
     }
 
+    /// Creates a new (non-ACK) `PingFrame` carrying the given opaque data.
+    ///
+    /// The peer is required to echo the same 8 octets back in its ACK, so
+    /// the data can serve as a token for matching the response.
+    pub fn with_data(data: [u8; 8]) -> PingFrame {
+        PingFrame {
+            data: data.to_vec(),
+            flags: 0,
+        }
+    }
+
     /// A convenience constructor that returns a `PingFrame` with the ACK
     /// flag already set and no data.
     pub fn new_ack() -> PingFrame {
@@ -106,8 +117,8 @@ impl Frame for PingFrame {
     ///
     /// `None` if a valid `PingFrame` cannot be contructed from the given
     /// `RawFrame`. The stream ID *MUST* be 0 in order for the frame to be
-    /// valid. If the `ACK` flag is set, there *MUST NOT* be a payload. The total
-    /// payload length must be 8 bytes long.
+    /// valid. The total payload length must be 8 bytes long, unless the `ACK`
+    /// flag is set, in which case an empty payload is also accepted.
     ///
     /// Otherwise, returns a newly constructed `PingFrame`.
     fn from_raw(raw_frame: RawFrame) -> Option<PingFrame> {
@@ -120,24 +131,19 @@ impl Frame for PingFrame {
         // Check that the length given in the header mathes the payload
         // length; if not, something went wrong and we do not consider this a
         // valid frame.
-        if (len as usize) != raw_frame.payload.len() {
+        if !raw_frame.is_consistent() {
             return None;
         }
         // Check that the PING frame is associated to stream 0
         if stream_id != 0 {
             return None;
         }
-        if (flags & PingFlag::Ack.bitmask()) != 0 {
-            if len != 0 {
-                // The PING flag MUST NOT have a payload if Ack is set
-                return None;
-            } else {
-                // Ack is set and there's no payload => just an Ack frame
-                return Some(PingFrame {
-                    data: Vec::new(),
-                    flags: flags,
-                });
-            }
+        if (flags & PingFlag::Ack.bitmask()) != 0 && len == 0 {
+            // Ack is set and there's no payload => just an Ack frame
+            return Some(PingFrame {
+                data: Vec::new(),
+                flags: flags,
+            });
         }
 
         match PingFrame::parse_payload(&raw_frame.payload) {
@@ -235,6 +241,36 @@ mod tests {
         assert!(frame.is_none());
     }
 
+    /// Tests that a PING frame created with some opaque data keeps it intact
+    /// when serialized and parsed back, without having the ACK flag set.
+    #[test]
+    fn test_ping_frame_with_data_round_trip() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let frame = PingFrame::with_data(data);
+        assert!(!frame.is_ack());
+
+        let serialized = frame.serialize();
+        let header = (8, 6, 0, 0);
+        let parsed = build_test_frame::<PingFrame>(&header, &serialized[9..]);
+
+        assert_eq!(parsed, frame);
+        assert_eq!(parsed.data, data.to_vec());
+    }
+
+    /// Tests that a PING ACK echoing the 8 octets of the original PING is
+    /// correctly parsed.
+    #[test]
+    fn test_ping_frame_parse_ack_with_payload() {
+        let payload = [8, 7, 6, 5, 4, 3, 2, 1];
+        // A header with the ACK flag set
+        let header = (payload.len() as u32, 6, 1, 0);
+
+        let frame = build_test_frame::<PingFrame>(&header, &payload);
+
+        assert!(frame.is_ack());
+        assert_eq!(frame.data, payload.to_vec());
+    }
+
     /// Tests that a `PingFrame` gets correctly serialized when it contains
     /// a payload and ACK.
     #[test]
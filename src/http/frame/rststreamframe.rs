@@ -0,0 +1,221 @@
+use super::super::{StreamId, ErrorCode};
+use super::frames::{
+    Frame,
+    Flag,
+    NoFlag,
+    RawFrame,
+    FrameHeader,
+    pack_header
+};
+
+/// A struct representing the RST_STREAM frames of HTTP/2, as defined in the
+/// HTTP/2 spec, section 6.4.
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct RstStreamFrame {
+    /// The ID of the stream that is being reset.
+    stream_id: StreamId,
+    /// The error code, as found on the wire. It is kept in its raw form so
+    /// that unknown codes are not lost.
+    raw_error_code: u32,
+    /// The flags set on the frame, packed into a single byte. No flags are
+    /// defined for RST_STREAM frames, but unknown flags are preserved.
+    flags: u8,
+}
+
+impl RstStreamFrame {
+    /// Creates a new `RstStreamFrame` that resets the stream with the given
+    /// ID with the given error code.
+    pub fn new(stream_id: StreamId, code: ErrorCode) -> RstStreamFrame {
+        RstStreamFrame {
+            stream_id: stream_id,
+            raw_error_code: code as u32,
+            flags: 0,
+        }
+    }
+
+    /// Returns the error code carried by the frame. Unknown error codes are
+    /// reported as an `ErrorCode::InternalError`.
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::from(self.raw_error_code)
+    }
+
+    /// Returns the error code carried by the frame, exactly as it is found
+    /// on the wire.
+    pub fn raw_error_code(&self) -> u32 {
+        self.raw_error_code
+    }
+
+    /// Returns the total length of the payload in bytes.
+    fn payload_len(&self) -> u32 {
+        4
+    }
+}
+
+impl Frame for RstStreamFrame {
+    /// RST_STREAM frames do not define any flags.
+    type FlagType = NoFlag;
+
+    /// Creates a new `RstStreamFrame` with the given `RawFrame` (i.e. header
+    /// and payload), if possible.
+    ///
+    /// # Returns
+    ///
+    /// `None` if a valid `RstStreamFrame` cannot be constructed from the
+    /// given `RawFrame`. The stream ID *must not* be 0 and the payload must
+    /// be exactly 4 bytes long.
+    ///
+    /// Otherwise, returns a newly constructed `RstStreamFrame`.
+    fn from_raw(raw_frame: RawFrame) -> Option<RstStreamFrame> {
+        // Unpack the header
+        let (len, frame_type, flags, stream_id) = raw_frame.header;
+        // Check that the frame type is correct for this frame implementation
+        if frame_type != 0x3 {
+            return None;
+        }
+        // Check that the length given in the header matches the payload
+        // length; if not, something went wrong and we do not consider this a
+        // valid frame.
+        if !raw_frame.is_consistent() {
+            return None;
+        }
+        // The error code is always exactly 4 bytes.
+        if len != 4 {
+            return None;
+        }
+        // A RST_STREAM frame cannot be associated to the connection itself.
+        if stream_id == 0 {
+            return None;
+        }
+        let payload = &raw_frame.payload;
+
+        Some(RstStreamFrame {
+            stream_id: stream_id,
+            raw_error_code: unpack_octets_4!(payload, 0, u32),
+            flags: flags,
+        })
+    }
+
+    /// Tests if the given flag is set for the frame.
+    fn is_set(&self, flag: NoFlag) -> bool {
+        (self.flags & flag.bitmask()) != 0
+    }
+
+    /// Returns the `StreamId` of the stream to which the frame is associated.
+    fn get_stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
+    /// Returns a `FrameHeader` based on the current state of the `Frame`.
+    fn get_header(&self) -> FrameHeader {
+        (self.payload_len(), 0x3, self.flags, self.stream_id)
+    }
+
+    /// Sets the given flag for the frame.
+    fn set_flag(&mut self, flag: NoFlag) {
+        self.flags |= flag.bitmask();
+    }
+
+    /// Returns a `Vec` with the serialized representation of the frame.
+    fn serialize(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(9 + self.payload_len() as usize);
+        // First the header...
+        buf.extend(pack_header(&self.get_header()).to_vec().into_iter());
+        // ...now the error code.
+        let code = self.raw_error_code;
+        buf.push(((code >> 24) & 0x000000FF) as u8);
+        buf.push(((code >> 16) & 0x000000FF) as u8);
+        buf.push(((code >>  8) & 0x000000FF) as u8);
+        buf.push(((code >>  0) & 0x000000FF) as u8);
+
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::ErrorCode;
+    use super::super::frames::{Frame, RawFrame, pack_header};
+    use super::super::test::{build_test_frame};
+    use super::RstStreamFrame;
+
+    /// Tests that a RST_STREAM frame is correctly parsed.
+    #[test]
+    fn test_rst_stream_frame_parse() {
+        let payload = [0, 0, 0, 0x7];
+        let header = (payload.len() as u32, 0x3, 0, 1);
+
+        let frame = build_test_frame::<RstStreamFrame>(&header, &payload);
+
+        assert_eq!(frame.error_code(), ErrorCode::RefusedStream);
+        assert_eq!(frame.get_stream_id(), 1);
+        assert_eq!(frame.get_header(), header);
+    }
+
+    /// Tests that a RST_STREAM frame with an unknown error code is parsed,
+    /// keeping the raw code intact.
+    #[test]
+    fn test_rst_stream_frame_parse_unknown_code() {
+        let payload = [0, 0, 1, 0];
+        let header = (payload.len() as u32, 0x3, 0, 1);
+
+        let frame = build_test_frame::<RstStreamFrame>(&header, &payload);
+
+        assert_eq!(frame.error_code(), ErrorCode::InternalError);
+        assert_eq!(frame.raw_error_code(), 256);
+    }
+
+    /// Tests that invalid RST_STREAM frames are rejected.
+    #[test]
+    fn test_rst_stream_frame_parse_invalid() {
+        // Associated to stream 0
+        {
+            let payload = vec![0, 0, 0, 0x8];
+            let header = (payload.len() as u32, 0x3, 0, 0);
+
+            let frame: Option<RstStreamFrame> = Frame::from_raw(
+                RawFrame::with_payload(header, payload));
+
+            assert!(frame.is_none());
+        }
+        // Payload not 4 bytes
+        {
+            let payload = vec![0, 0, 0x8];
+            let header = (payload.len() as u32, 0x3, 0, 1);
+
+            let frame: Option<RstStreamFrame> = Frame::from_raw(
+                RawFrame::with_payload(header, payload));
+
+            assert!(frame.is_none());
+        }
+        // Wrong frame type
+        {
+            let payload = vec![0, 0, 0, 0x8];
+            let header = (payload.len() as u32, 0x8, 0, 1);
+
+            let frame: Option<RstStreamFrame> = Frame::from_raw(
+                RawFrame::with_payload(header, payload));
+
+            assert!(frame.is_none());
+        }
+    }
+
+    /// Tests that a `RstStreamFrame` built from an `ErrorCode` is correctly
+    /// serialized.
+    #[test]
+    fn test_rst_stream_frame_serialize_cancel() {
+        let frame = RstStreamFrame::new(3, ErrorCode::Cancel);
+        let expected = {
+            let mut res: Vec<u8> = Vec::new();
+            res.extend(pack_header(&(4, 0x3, 0, 3)).to_vec().into_iter());
+            res.extend(vec![0, 0, 0, 0x8].into_iter());
+
+            res
+        };
+
+        let serialized = frame.serialize();
+
+        assert_eq!(serialized, expected);
+        assert_eq!(frame.raw_error_code(), 0x8);
+    }
+}
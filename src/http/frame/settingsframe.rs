@@ -181,6 +181,34 @@ impl SettingsFrame {
         self.is_set(SettingsFlag::Ack)
     }
 
+    /// Returns a `Vec` with the serialized representation of the frame, where
+    /// the settings appear in ascending order of their identifiers (the
+    /// canonical order), instead of the order in which they were added.
+    ///
+    /// Settings with the same identifier keep their relative order, since
+    /// the peer applies them in order of appearance.
+    pub fn serialize_canonical(&self) -> Vec<u8> {
+        let mut settings: Vec<&HttpSetting> = self.settings.iter().collect();
+        // The sort is stable, so duplicates keep their relative order.
+        settings.sort_by(|a, b| a.get_id().cmp(&b.get_id()));
+
+        self.serialize_settings(settings)
+    }
+
+    /// Serializes the frame, placing the given settings in the payload in
+    /// the given order.
+    fn serialize_settings(&self, settings: Vec<&HttpSetting>) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.payload_len() as usize);
+        // First the header...
+        buf.extend(pack_header(&self.get_header()).to_vec().into_iter());
+        // ...now the settings
+        for setting in settings.into_iter() {
+            buf.extend(setting.serialize().to_vec().into_iter());
+        }
+
+        buf
+    }
+
     /// Returns the total length of the payload in bytes.
     fn payload_len(&self) -> u32 {
         // Each setting is represented with 6 bytes =>
@@ -237,7 +265,7 @@ impl Frame for SettingsFrame {
         // Check that the length given in the header matches the payload
         // length; if not, something went wrong and we do not consider this a
         // valid frame.
-        if (len as usize) != raw_frame.payload.len() {
+        if !raw_frame.is_consistent() {
             return None;
         }
         // Check that the SETTINGS frame is associated to stream 0
@@ -292,15 +320,7 @@ impl Frame for SettingsFrame {
 
     /// Returns a `Vec` with the serialized representation of the frame.
     fn serialize(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(self.payload_len() as usize);
-        // First the header...
-        buf.extend(pack_header(&self.get_header()).to_vec().into_iter());
-        // ...now the settings
-        for setting in self.settings.iter() {
-            buf.extend(setting.serialize().to_vec().into_iter());
-        }
-
-        buf
+        self.serialize_settings(self.settings.iter().collect())
     }
 }
 
@@ -606,6 +626,37 @@ mod tests {
         assert_eq!(serialized, expected);
     }
 
+    /// Tests that the canonical serialization of a `SettingsFrame` has its
+    /// settings sorted by identifier, keeping duplicates in the order in
+    /// which they were added, while the regular serialization keeps the
+    /// insertion order.
+    #[test]
+    fn test_settings_frame_serialize_canonical_order() {
+        let settings = vec![
+            HttpSetting::MaxFrameSize(16384),
+            HttpSetting::InitialWindowSize(10),
+            HttpSetting::EnablePush(0),
+            HttpSetting::InitialWindowSize(20),
+        ];
+        let build = |order: &[usize]| {
+            let mut res: Vec<u8> = Vec::new();
+            res.extend(pack_header(&(6 * 4, 4, 0, 0)).to_vec().into_iter());
+            for &i in order.iter() {
+                res.extend(settings[i].serialize().to_vec().into_iter());
+            }
+            res
+        };
+        let mut frame = SettingsFrame::new();
+        for setting in settings.iter() {
+            frame.add_setting(setting.clone());
+        }
+
+        assert_eq!(frame.serialize(), build(&[0, 1, 2, 3]));
+        assert_eq!(frame.serialize_canonical(), build(&[2, 1, 3, 0]));
+        // The frame itself is left as it was.
+        assert_eq!(frame.settings, settings);
+    }
+
     /// Tests that a `SettingsFrame` gets correctly serialized when it contains
     /// multiple settings and no ACK.
     #[test]
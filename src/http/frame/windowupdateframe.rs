@@ -0,0 +1,249 @@
+use super::super::StreamId;
+use super::frames::{
+    Frame,
+    Flag,
+    NoFlag,
+    RawFrame,
+    FrameHeader,
+    pack_header
+};
+
+/// The largest window size increment that a WINDOW_UPDATE frame can carry
+/// (2^31 - 1), as defined in section 6.9.
+pub const MAX_WINDOW_SIZE_INCREMENT: u32 = (1 << 31) - 1;
+
+/// A struct representing the WINDOW_UPDATE frames of HTTP/2, as defined in the
+/// HTTP/2 spec, section 6.9.
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct WindowUpdateFrame {
+    /// The ID of the stream with which the frame is associated. A stream ID
+    /// of 0 means that the frame applies to the entire connection.
+    stream_id: StreamId,
+    /// The number of bytes that the window is incremented by.
+    increment: u32,
+    /// The flags set on the frame, packed into a single byte. No flags are
+    /// defined for WINDOW_UPDATE frames, but unknown flags are preserved.
+    flags: u8,
+}
+
+impl WindowUpdateFrame {
+    /// Creates a new `WindowUpdateFrame` that increments the window of the
+    /// stream with the given ID (or of the connection, for stream 0) by the
+    /// given number of bytes.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the increment is not valid, i.e. if it is 0 or larger than
+    /// 2^31 - 1.
+    pub fn new(stream_id: StreamId, increment: u32) -> Option<WindowUpdateFrame> {
+        if increment == 0 || increment > MAX_WINDOW_SIZE_INCREMENT {
+            return None;
+        }
+
+        Some(WindowUpdateFrame {
+            stream_id: stream_id,
+            increment: increment,
+            flags: 0,
+        })
+    }
+
+    /// Returns the window size increment carried by the frame.
+    pub fn increment(&self) -> u32 {
+        self.increment
+    }
+
+    /// Returns the total length of the payload in bytes.
+    fn payload_len(&self) -> u32 {
+        4
+    }
+}
+
+impl Frame for WindowUpdateFrame {
+    /// WINDOW_UPDATE frames do not define any flags.
+    type FlagType = NoFlag;
+
+    /// Creates a new `WindowUpdateFrame` with the given `RawFrame` (i.e.
+    /// header and payload), if possible.
+    ///
+    /// # Returns
+    ///
+    /// `None` if a valid `WindowUpdateFrame` cannot be constructed from the
+    /// given `RawFrame`. The payload must be exactly 4 bytes long. The
+    /// reserved (most significant) bit of the increment is ignored.
+    ///
+    /// An increment of 0 is not considered a framing error, so it is up to
+    /// the receiver of the frame to handle it.
+    ///
+    /// Otherwise, returns a newly constructed `WindowUpdateFrame`.
+    fn from_raw(raw_frame: RawFrame) -> Option<WindowUpdateFrame> {
+        // Unpack the header
+        let (len, frame_type, flags, stream_id) = raw_frame.header;
+        // Check that the frame type is correct for this frame implementation
+        if frame_type != 0x8 {
+            return None;
+        }
+        // Check that the length given in the header matches the payload
+        // length; if not, something went wrong and we do not consider this a
+        // valid frame.
+        if !raw_frame.is_consistent() {
+            return None;
+        }
+        // The increment is always exactly 4 bytes.
+        if len != 4 {
+            return None;
+        }
+        let payload = &raw_frame.payload;
+        let increment = unpack_octets_4!(payload, 0, u32) & MAX_WINDOW_SIZE_INCREMENT;
+
+        Some(WindowUpdateFrame {
+            stream_id: stream_id,
+            increment: increment,
+            flags: flags,
+        })
+    }
+
+    /// Tests if the given flag is set for the frame.
+    fn is_set(&self, flag: NoFlag) -> bool {
+        (self.flags & flag.bitmask()) != 0
+    }
+
+    /// Returns the `StreamId` of the stream to which the frame is associated.
+    fn get_stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
+    /// Returns a `FrameHeader` based on the current state of the `Frame`.
+    fn get_header(&self) -> FrameHeader {
+        (self.payload_len(), 0x8, self.flags, self.stream_id)
+    }
+
+    /// Sets the given flag for the frame.
+    fn set_flag(&mut self, flag: NoFlag) {
+        self.flags |= flag.bitmask();
+    }
+
+    /// Returns a `Vec` with the serialized representation of the frame.
+    fn serialize(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(9 + self.payload_len() as usize);
+        // First the header...
+        buf.extend(pack_header(&self.get_header()).to_vec().into_iter());
+        // ...now the increment, with the reserved bit cleared.
+        let increment = self.increment & MAX_WINDOW_SIZE_INCREMENT;
+        buf.push(((increment >> 24) & 0x000000FF) as u8);
+        buf.push(((increment >> 16) & 0x000000FF) as u8);
+        buf.push(((increment >>  8) & 0x000000FF) as u8);
+        buf.push(((increment >>  0) & 0x000000FF) as u8);
+
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::frames::{Frame, RawFrame, pack_header};
+    use super::super::test::{build_test_frame};
+    use super::{WindowUpdateFrame, MAX_WINDOW_SIZE_INCREMENT};
+
+    /// Tests that a WINDOW_UPDATE frame is correctly parsed.
+    #[test]
+    fn test_window_update_frame_parse() {
+        let payload = [0, 0, 1, 0];
+        let header = (payload.len() as u32, 0x8, 0, 3);
+
+        let frame = build_test_frame::<WindowUpdateFrame>(&header, &payload);
+
+        assert_eq!(frame.increment(), 256);
+        assert_eq!(frame.get_stream_id(), 3);
+        assert_eq!(frame.get_header(), header);
+    }
+
+    /// Tests that the reserved bit of the increment is ignored when parsing
+    /// a WINDOW_UPDATE frame.
+    #[test]
+    fn test_window_update_frame_parse_reserved_bit() {
+        let payload = [0x80, 0, 0, 1];
+        let header = (payload.len() as u32, 0x8, 0, 0);
+
+        let frame = build_test_frame::<WindowUpdateFrame>(&header, &payload);
+
+        assert_eq!(frame.increment(), 1);
+    }
+
+    /// Tests that a WINDOW_UPDATE frame whose payload is not exactly 4 bytes
+    /// long is considered invalid.
+    #[test]
+    fn test_window_update_frame_parse_invalid_length() {
+        {
+            let payload = vec![0, 0, 1];
+            let header = (payload.len() as u32, 0x8, 0, 1);
+
+            let frame: Option<WindowUpdateFrame> = Frame::from_raw(
+                RawFrame::with_payload(header, payload));
+
+            assert!(frame.is_none());
+        }
+        {
+            let payload = vec![0, 0, 0, 1, 0];
+            let header = (payload.len() as u32, 0x8, 0, 1);
+
+            let frame: Option<WindowUpdateFrame> = Frame::from_raw(
+                RawFrame::with_payload(header, payload));
+
+            assert!(frame.is_none());
+        }
+    }
+
+    /// Tests that a frame with a type other than WINDOW_UPDATE is not parsed
+    /// as a `WindowUpdateFrame`.
+    #[test]
+    fn test_window_update_frame_parse_invalid_type() {
+        let payload = vec![0, 0, 0, 1];
+        let header = (payload.len() as u32, 0x6, 0, 1);
+
+        let frame: Option<WindowUpdateFrame> = Frame::from_raw(
+            RawFrame::with_payload(header, payload));
+
+        assert!(frame.is_none());
+    }
+
+    /// Tests that a `WindowUpdateFrame` is correctly serialized.
+    #[test]
+    fn test_window_update_frame_serialize() {
+        let frame = WindowUpdateFrame::new(1, 0x01020304).unwrap();
+        let expected = {
+            let mut res: Vec<u8> = Vec::new();
+            res.extend(pack_header(&(4, 0x8, 0, 1)).to_vec().into_iter());
+            res.extend(vec![1, 2, 3, 4].into_iter());
+
+            res
+        };
+
+        let serialized = frame.serialize();
+
+        assert_eq!(serialized, expected);
+    }
+
+    /// Tests that a serialized `WindowUpdateFrame` is parsed back into an
+    /// equal frame.
+    #[test]
+    fn test_window_update_frame_roundtrip() {
+        let frame = WindowUpdateFrame::new(0, MAX_WINDOW_SIZE_INCREMENT).unwrap();
+
+        let serialized = frame.serialize();
+        let raw = RawFrame::from_buf(&serialized).unwrap();
+        let parsed: WindowUpdateFrame = Frame::from_raw(raw).unwrap();
+
+        assert_eq!(parsed, frame);
+        assert_eq!(parsed.increment(), MAX_WINDOW_SIZE_INCREMENT);
+    }
+
+    /// Tests that a `WindowUpdateFrame` cannot be created with an invalid
+    /// increment.
+    #[test]
+    fn test_window_update_frame_new_invalid_increment() {
+        assert!(WindowUpdateFrame::new(1, 0).is_none());
+        assert!(WindowUpdateFrame::new(1, MAX_WINDOW_SIZE_INCREMENT + 1).is_none());
+        assert!(WindowUpdateFrame::new(1, 1).is_some());
+    }
+}
@@ -30,6 +30,59 @@ pub const ALPN_PROTOCOLS: &'static [&'static [u8]] = &[
     b"h2-14",
 ];
 
+/// An enum representing the error codes that are used in RST_STREAM and
+/// GOAWAY frames to convey the reason for the stream or connection error,
+/// as defined in section 7. of the HTTP/2 spec.
+///
+/// The integer representation associated to each variant is the code that
+/// is sent on the wire.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Copy)]
+pub enum ErrorCode {
+    NoError = 0x0,
+    ProtocolError = 0x1,
+    InternalError = 0x2,
+    FlowControlError = 0x3,
+    SettingsTimeout = 0x4,
+    StreamClosed = 0x5,
+    FrameSizeError = 0x6,
+    RefusedStream = 0x7,
+    Cancel = 0x8,
+    CompressionError = 0x9,
+    ConnectError = 0xa,
+    EnhanceYourCalm = 0xb,
+    InadequateSecurity = 0xc,
+    Http11Required = 0xd,
+}
+
+/// Converts a raw error code, as received on the wire, into an `ErrorCode`.
+///
+/// Since unknown error codes MUST NOT trigger any special behavior, they
+/// are treated as an `InternalError`, as the spec allows (section 7.).
+impl From<u32> for ErrorCode {
+    fn from(code: u32) -> ErrorCode {
+        match code {
+            0x0 => ErrorCode::NoError,
+            0x1 => ErrorCode::ProtocolError,
+            0x2 => ErrorCode::InternalError,
+            0x3 => ErrorCode::FlowControlError,
+            0x4 => ErrorCode::SettingsTimeout,
+            0x5 => ErrorCode::StreamClosed,
+            0x6 => ErrorCode::FrameSizeError,
+            0x7 => ErrorCode::RefusedStream,
+            0x8 => ErrorCode::Cancel,
+            0x9 => ErrorCode::CompressionError,
+            0xa => ErrorCode::ConnectError,
+            0xb => ErrorCode::EnhanceYourCalm,
+            0xc => ErrorCode::InadequateSecurity,
+            0xd => ErrorCode::Http11Required,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
 /// An enum representing errors that can arise when performing operations
 /// involving an HTTP/2 connection.
 #[derive(Debug)]
@@ -41,6 +94,9 @@ pub enum HttpError {
     UnknownStreamId,
     UnableToConnect,
     MalformedResponse,
+    InvalidSettings,
+    FrameSizeError,
+    FlowControlError,
 }
 
 /// Implement the trait that allows us to automatically convert `io::Error`s
@@ -67,6 +123,9 @@ impl PartialEq for HttpError {
             (&HttpError::UnknownStreamId, &HttpError::UnknownStreamId) => true,
             (&HttpError::UnableToConnect, &HttpError::UnableToConnect) => true,
             (&HttpError::MalformedResponse, &HttpError::MalformedResponse) => true,
+            (&HttpError::InvalidSettings, &HttpError::InvalidSettings) => true,
+            (&HttpError::FrameSizeError, &HttpError::FrameSizeError) => true,
+            (&HttpError::FlowControlError, &HttpError::FlowControlError) => true,
             _ => false,
         }
     }
@@ -178,7 +237,7 @@ pub struct Request {
 
 #[cfg(test)]
 mod tests {
-    use super::{Response, HttpError, HttpScheme};
+    use super::{Response, HttpError, HttpScheme, ErrorCode};
 
     /// Tests that the `Response` struct correctly parses a status code from
     /// its headers list.
@@ -226,4 +285,18 @@ mod tests {
         assert_eq!(HttpScheme::Http.as_bytes(), b"http");
         assert_eq!(HttpScheme::Https.as_bytes(), b"https");
     }
+
+    /// Tests that raw error codes are correctly converted into `ErrorCode`s
+    /// and that unknown codes are treated as internal errors.
+    #[test]
+    fn test_error_code_from_u32() {
+        assert_eq!(ErrorCode::from(0x0), ErrorCode::NoError);
+        assert_eq!(ErrorCode::from(0x8), ErrorCode::Cancel);
+        assert_eq!(ErrorCode::from(0xd), ErrorCode::Http11Required);
+        assert_eq!(ErrorCode::from(0xe), ErrorCode::InternalError);
+        // Converting back gives the same code.
+        for code in 0..0xe {
+            assert_eq!(ErrorCode::from(code) as u32, code);
+        }
+    }
 }
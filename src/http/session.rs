@@ -12,7 +12,8 @@
 //! streams are active.
 use std::collections::HashMap;
 use std::iter::FromIterator;
-use super::{StreamId, Header};
+use super::{StreamId, Header, ErrorCode};
+use super::frame::RstStreamFrame;
 
 /// A trait that defines methods that need to be defined in order to track the
 /// status of a `ClientConnection`.
@@ -127,6 +128,8 @@ impl Stream for DefaultStream {
 /// having to change any HTTP/2-specific logic.
 pub struct DefaultSession<S=DefaultStream> where S: Stream {
     streams: HashMap<StreamId, S>,
+    /// The number of tracked streams that are not yet closed.
+    active_streams: u32,
 }
 
 impl<S> DefaultSession<S> where S: Stream {
@@ -134,6 +137,20 @@ impl<S> DefaultSession<S> where S: Stream {
     pub fn new() -> DefaultSession<S> {
         DefaultSession {
             streams: HashMap::new(),
+            active_streams: 0,
+        }
+    }
+
+    /// Returns a new `DefaultSession` with no active streams, whose stream
+    /// map is pre-allocated to hold at least `expected_streams` streams
+    /// without reallocating.
+    ///
+    /// This is useful when the number of concurrent streams that the session
+    /// is going to track is known upfront.
+    pub fn with_capacity(expected_streams: usize) -> DefaultSession<S> {
+        DefaultSession {
+            streams: HashMap::with_capacity(expected_streams),
+            active_streams: 0,
         }
     }
 
@@ -143,9 +160,97 @@ impl<S> DefaultSession<S> where S: Stream {
         self.streams.get(&stream_id)
     }
 
+    /// Returns whether the stream with the given ID is tracked by the session
+    /// and has not yet been closed.
+    pub fn is_active(&self, stream_id: StreamId) -> bool {
+        match self.streams.get(&stream_id) {
+            Some(stream) => !stream.is_closed(),
+            None => false,
+        }
+    }
+
+    /// Returns the number of streams that are tracked by the session and
+    /// have not yet been closed.
+    #[inline]
+    pub fn active_count(&self) -> u32 {
+        self.active_streams
+    }
+
+    /// Returns whether the stream with the given ID is closed.
+    ///
+    /// Streams that are not tracked by the session (either because they were
+    /// never created or because they were already removed from it by
+    /// `get_closed`) are also considered closed.
+    pub fn is_closed(&self, stream_id: StreamId) -> bool {
+        !self.is_active(stream_id)
+    }
+
+    /// Returns the IDs of all active streams whose ID is greater than the
+    /// given `last_stream_id`, in ascending order.
+    ///
+    /// After a GOAWAY frame with the given last stream ID, these are the
+    /// streams that were not processed by the peer and could be retried on
+    /// a new connection.
+    pub fn streams_above(&self, last_stream_id: StreamId) -> Vec<StreamId> {
+        let mut ids: Vec<_> = self.streams.iter()
+                                  .filter_map(|(i, s)| {
+                                      if *i > last_stream_id && !s.is_closed() {
+                                          Some(*i)
+                                      } else {
+                                          None
+                                      }
+                                  })
+                                  .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Returns the IDs of the streams that the client initiated and that the
+    /// server did not process before sending a GOAWAY frame with the given
+    /// last stream ID, in ascending order.
+    ///
+    /// Such requests are guaranteed not to have been acted upon by the server
+    /// and can, therefore, safely be retried on a new connection.
+    pub fn retryable_streams(&self, goaway_last_id: StreamId) -> Vec<StreamId> {
+        // Client-initiated streams always have odd IDs.
+        self.streams_above(goaway_last_id).into_iter()
+                                          .filter(|id| id % 2 == 1)
+                                          .collect()
+    }
+
     /// Creates a new stream with the given ID in the session.
     pub fn new_stream(&mut self, stream_id: StreamId) {
-        self.streams.insert(stream_id, Stream::new(stream_id));
+        let replaced = self.streams.insert(stream_id, Stream::new(stream_id));
+        // Replacing a stream that was still active leaves the count as is.
+        match replaced {
+            Some(ref stream) if !stream.is_closed() => {},
+            _ => self.active_streams += 1,
+        }
+    }
+
+    /// Cancels the stream with the given ID by closing it locally.
+    ///
+    /// # Returns
+    ///
+    /// The `RstStreamFrame` carrying the given error code, which should be
+    /// sent to the peer to let it know that the stream was cancelled.
+    ///
+    /// If the stream is not tracked by the session or it is already closed,
+    /// `None` is returned, as there is nothing to cancel.
+    pub fn cancel_stream(&mut self, stream_id: StreamId, code: ErrorCode)
+            -> Option<RstStreamFrame> {
+        let stream = match self.streams.get_mut(&stream_id) {
+            Some(stream) => stream,
+            None => return None,
+        };
+        if stream.is_closed() {
+            return None;
+        }
+        debug!("Cancelling stream {}", stream_id);
+        stream.close();
+        self.active_streams -= 1;
+
+        Some(RstStreamFrame::new(stream_id, code))
     }
 
     /// Returns all streams that are closed and tracked by the session.
@@ -197,17 +302,56 @@ impl<S> Session for DefaultSession<S> where S: Stream {
             },
             Some(stream) => stream,
         };
+        // Only a stream that was still active decrements the count, so that
+        // closing a stream twice does not count it twice.
+        if !stream.is_closed() {
+            self.active_streams -= 1;
+        }
         stream.close()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::ErrorCode;
+    use super::super::frame::Frame;
     use super::{
         Session, DefaultSession,
         Stream,
     };
 
+    /// Tests that the `DefaultSession` keeps an accurate count of its active
+    /// streams as streams are opened and closed.
+    #[test]
+    fn test_default_session_active_count() {
+        let mut session: DefaultSession = DefaultSession::new();
+        assert_eq!(session.active_count(), 0);
+
+        session.new_stream(1);
+        session.new_stream(3);
+        session.new_stream(5);
+        assert_eq!(session.active_count(), 3);
+        // Re-creating an active stream does not count it twice.
+        session.new_stream(5);
+        assert_eq!(session.active_count(), 3);
+
+        session.end_of_stream(1);
+        assert_eq!(session.active_count(), 2);
+        // Closing an already closed stream does not decrement the count again
+        session.end_of_stream(1);
+        assert!(session.cancel_stream(1, ErrorCode::Cancel).is_none());
+        assert_eq!(session.active_count(), 2);
+        // Nor does closing an unknown stream
+        session.end_of_stream(7);
+        assert_eq!(session.active_count(), 2);
+
+        session.cancel_stream(3, ErrorCode::Cancel).unwrap();
+        assert_eq!(session.active_count(), 1);
+        // Removing the closed streams does not affect the count.
+        session.get_closed();
+        assert_eq!(session.active_count(), 1);
+    }
+
     /// Tests that a `DefaultSession` notifies the correct stream when the
     /// appropriate callback is invoked.
     ///
@@ -252,4 +396,107 @@ mod tests {
         // ...and is also removed from the session!
         assert_eq!(session.streams.len(), 1);
     }
+
+    /// Tests that a `DefaultSession` created with a capacity hint behaves the
+    /// same as one created by `new`, even when the expected number of streams
+    /// is reached.
+    #[test]
+    fn test_default_session_with_capacity() {
+        let mut session: DefaultSession = DefaultSession::with_capacity(3);
+        assert!(session.streams.capacity() >= 3);
+
+        for &id in [1, 3, 5].iter() {
+            session.new_stream(id);
+            session.new_data_chunk(id, &[id as u8]);
+        }
+
+        assert_eq!(session.streams.len(), 3);
+        for &id in [1, 3, 5].iter() {
+            assert_eq!(session.get_stream(id).unwrap().body, vec![id as u8]);
+        }
+        // Going past the hint is still fine.
+        session.new_stream(7);
+        assert_eq!(session.streams.len(), 4);
+    }
+
+    /// Tests that the `DefaultSession` correctly reports whether a stream is
+    /// active or closed.
+    #[test]
+    fn test_default_session_is_active_is_closed() {
+        let mut session: DefaultSession = DefaultSession::new();
+        // An unknown stream is closed
+        assert!(!session.is_active(1));
+        assert!(session.is_closed(1));
+
+        // A newly created stream is active
+        session.new_stream(1);
+        assert!(session.is_active(1));
+        assert!(!session.is_closed(1));
+
+        // A stream that was ended by the peer is closed...
+        session.end_of_stream(1);
+        assert!(!session.is_active(1));
+        assert!(session.is_closed(1));
+        // ...and stays closed once it's removed from the session.
+        session.get_closed();
+        assert!(!session.is_active(1));
+        assert!(session.is_closed(1));
+    }
+
+    /// Tests that the `DefaultSession` correctly returns the active streams
+    /// above a given stream ID.
+    #[test]
+    fn test_default_session_streams_above() {
+        let mut session: DefaultSession = DefaultSession::new();
+        session.new_stream(1);
+        session.new_stream(3);
+        session.new_stream(5);
+
+        assert_eq!(session.streams_above(3), vec![5]);
+        assert_eq!(session.streams_above(0), vec![1, 3, 5]);
+        assert_eq!(session.streams_above(5), vec![]);
+
+        // Closed streams are not included.
+        session.new_stream(7);
+        session.end_of_stream(5);
+        assert_eq!(session.streams_above(3), vec![7]);
+    }
+
+    /// Tests that only the active, client-initiated streams above the GOAWAY
+    /// last stream ID are reported as retryable.
+    #[test]
+    fn test_default_session_retryable_streams() {
+        let mut session: DefaultSession = DefaultSession::new();
+        session.new_stream(1);
+        session.new_stream(3);
+        session.new_stream(5);
+
+        assert_eq!(session.retryable_streams(1), vec![3, 5]);
+
+        // A (server-initiated) even stream is never retryable...
+        session.new_stream(2);
+        assert_eq!(session.retryable_streams(0), vec![1, 3, 5]);
+        // ...and neither is a closed one.
+        session.end_of_stream(5);
+        assert_eq!(session.retryable_streams(1), vec![3]);
+    }
+
+    /// Tests that cancelling an active stream closes it and returns the
+    /// appropriate RST_STREAM frame, while cancelling a stream that is closed
+    /// or unknown does nothing.
+    #[test]
+    fn test_default_session_cancel_stream() {
+        let mut session: DefaultSession = DefaultSession::new();
+        session.new_stream(1);
+
+        let frame = session.cancel_stream(1, ErrorCode::Cancel).unwrap();
+
+        assert_eq!(frame.get_stream_id(), 1);
+        assert_eq!(frame.error_code(), ErrorCode::Cancel);
+        assert!(session.is_closed(1));
+        // Already closed => nothing to send.
+        assert!(session.cancel_stream(1, ErrorCode::Cancel).is_none());
+        // Unknown stream => nothing to send.
+        assert!(session.cancel_stream(3, ErrorCode::Cancel).is_none());
+    }
 }